@@ -0,0 +1,109 @@
+use crate::parsers::{Checksum, ChecksumAlgorithm, DIGEST_SIZE};
+use futures::{
+	io::Error,
+	task::{Context, Poll},
+	AsyncRead,
+};
+use sha2::{Digest, Sha256};
+use std::pin::Pin;
+
+/// Computes digests of the same data with several algorithms at once.
+pub(crate) struct Digests {
+	hashers: Vec<Hasher>,
+}
+
+enum Hasher {
+	Blake3(Box<blake3::Hasher>),
+	Sha256(Sha256),
+}
+
+impl Digests {
+	pub fn new(algorithms: impl IntoIterator<Item = ChecksumAlgorithm>) -> Self {
+		Self {
+			hashers: algorithms
+				.into_iter()
+				.map(|algorithm| match algorithm {
+					ChecksumAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+					ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+				})
+				.collect(),
+		}
+	}
+
+	/// Digest some bytes in one go.
+	pub fn of(
+		algorithms: &[ChecksumAlgorithm],
+		bytes: &[u8],
+	) -> Vec<(ChecksumAlgorithm, [u8; DIGEST_SIZE])> {
+		let mut digests = Self::new(algorithms.iter().copied());
+		digests.update(bytes);
+		digests.finish()
+	}
+
+	pub fn update(&mut self, bytes: &[u8]) {
+		for hasher in &mut self.hashers {
+			match hasher {
+				Hasher::Blake3(hasher) => {
+					hasher.update(bytes);
+				}
+				Hasher::Sha256(hasher) => hasher.update(bytes),
+			}
+		}
+	}
+
+	pub fn finish(self) -> Vec<(ChecksumAlgorithm, [u8; DIGEST_SIZE])> {
+		self.hashers
+			.into_iter()
+			.map(|hasher| match hasher {
+				Hasher::Blake3(hasher) => {
+					(ChecksumAlgorithm::Blake3, *hasher.finalize().as_bytes())
+				}
+				Hasher::Sha256(hasher) => (ChecksumAlgorithm::Sha256, hasher.finalize().into()),
+			})
+			.collect()
+	}
+
+	/// Finish and compare against the expected checksums, returning the first algorithm whose
+	/// digest doesn't match.
+	pub fn check(self, expected: &[Checksum]) -> Option<ChecksumAlgorithm> {
+		self.finish().into_iter().find_map(|(algorithm, digest)| {
+			expected
+				.iter()
+				.any(|sum| sum.algorithm == algorithm && sum.digest != digest)
+				.then_some(algorithm)
+		})
+	}
+}
+
+/// Digests whatever is read through it.
+pub(crate) struct Hashing<R> {
+	inner: R,
+	digests: Digests,
+}
+
+impl<R> Hashing<R> {
+	pub fn new(inner: R, algorithms: &[ChecksumAlgorithm]) -> Self {
+		Self {
+			inner,
+			digests: Digests::new(algorithms.iter().copied()),
+		}
+	}
+
+	pub fn finish(self) -> Vec<(ChecksumAlgorithm, [u8; DIGEST_SIZE])> {
+		self.digests.finish()
+	}
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Hashing<R> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut [u8],
+	) -> Poll<Result<usize, Error>> {
+		let read = Pin::new(&mut self.inner).poll_read(cx, buf);
+		if let Poll::Ready(Ok(n)) = read {
+			self.digests.update(&buf[..n]);
+		}
+		read
+	}
+}
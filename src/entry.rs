@@ -0,0 +1,208 @@
+use crate::{
+	checksum::Digests,
+	parsers::{Checksum, Encoding, EntryHeader, Indic, ZstdParams},
+	SourceState, TomoError,
+};
+use async_compression::futures::bufread::ZstdDecoder;
+use deku::DekuContainerRead;
+use futures::{
+	io::{BufReader, Error, ErrorKind},
+	task::{Context, Poll},
+	AsyncRead,
+};
+use std::{fmt, pin::Pin, sync::Arc};
+
+/// Reads the decoded data of an entry.
+///
+/// Obtained from [`Tomo::open`](crate::Tomo::open) or an [`EntriesStream`](crate::EntriesStream).
+/// Holds an exclusive borrow on the source the entry is in, and reads directly from it without
+/// buffering the whole entry.
+pub struct EntryReader<'a> {
+	header: EntryHeader,
+	data_start: u64,
+	encoded_len: u64,
+	inner: Box<dyn AsyncRead + Unpin + 'a>,
+	verify: Option<Verify>,
+}
+
+/// Checksums to compare against once the end of the data is reached.
+struct Verify {
+	indic: u64,
+	digests: Digests,
+	expected: Vec<Checksum>,
+}
+
+impl fmt::Debug for EntryReader<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("EntryReader")
+			.field("header", &self.header)
+			.field("data_start", &self.data_start)
+			.field("encoded_len", &self.encoded_len)
+			.field("inner", &"<boxed async reader>")
+			.field("verify", &self.verify.as_ref().map(|v| &v.expected))
+			.finish()
+	}
+}
+
+impl<'a> EntryReader<'a> {
+	/// Read the header of the entry an indic points to, and set up to read its data.
+	///
+	/// If the entry is compressed with a zstd dictionary, that is loaded first.
+	pub(crate) async fn new<'s: 'a>(
+		source: &'a mut SourceState<'s>,
+		container: usize,
+		indic: &Indic,
+	) -> Result<EntryReader<'a>, TomoError> {
+		let (header, data_start) = source.entry_header(container, indic).await?;
+		let dictionary = match header.encoding() {
+			Encoding::Zstd if !header.params().is_empty() => {
+				let (_, params) = ZstdParams::from_bytes((header.params(), 0))?;
+				Some(source.dictionary(container, params.dictionary).await?)
+			}
+			_ => None,
+		};
+
+		Self::decode(source, container, indic, header, data_start, dictionary).await
+	}
+
+	/// Same as [`EntryReader::new`], but fails if the entry needs a dictionary.
+	///
+	/// This is used to read dictionaries themselves, which can't depend on other dictionaries.
+	pub(crate) async fn without_dictionary<'s: 'a>(
+		source: &'a mut SourceState<'s>,
+		container: usize,
+		indic: &Indic,
+	) -> Result<EntryReader<'a>, TomoError> {
+		let (header, data_start) = source.entry_header(container, indic).await?;
+		if header.encoding() == Encoding::Zstd && !header.params().is_empty() {
+			let (_, params) = ZstdParams::from_bytes((header.params(), 0))?;
+			return Err(TomoError::MissingDictionary(params.dictionary));
+		}
+
+		Self::decode(source, container, indic, header, data_start, None).await
+	}
+
+	async fn decode<'s: 'a>(
+		source: &'a mut SourceState<'s>,
+		container: usize,
+		indic: &Indic,
+		header: EntryHeader,
+		data_start: u64,
+		dictionary: Option<Arc<Vec<u8>>>,
+	) -> Result<EntryReader<'a>, TomoError> {
+		let entry_start = source.entries_start(container) + indic.offset;
+		let length = indic.length.saturating_sub(data_start - entry_start);
+
+		// loading a dictionary may have moved the source elsewhere
+		source.seek_to(data_start).await?;
+
+		let raw = RawReader {
+			source,
+			left: length,
+		};
+
+		let inner: Box<dyn AsyncRead + Unpin + 'a> = match (header.encoding(), dictionary) {
+			(Encoding::Raw, _) => Box::new(raw),
+			(Encoding::Zstd, None) => Box::new(ZstdDecoder::new(BufReader::new(raw))),
+			(Encoding::Zstd, Some(dict)) => {
+				Box::new(ZstdDecoder::with_dict(BufReader::new(raw), &dict)?)
+			}
+			(other, _) => return Err(TomoError::UnsupportedEncoding(other)),
+		};
+
+		Ok(Self {
+			header,
+			data_start,
+			encoded_len: length,
+			inner,
+			verify: None,
+		})
+	}
+
+	/// Check the decoded data against the checksums of its indic, given by position.
+	///
+	/// Once the end of the data is reached, reading fails with an [`ErrorKind::InvalidData`]
+	/// error wrapping [`TomoError::ChecksumMismatch`] if any digest doesn't match. Data that
+	/// isn't read to the end isn't checked.
+	pub(crate) fn verify(mut self, indic: u64, expected: Vec<Checksum>) -> Self {
+		if !expected.is_empty() {
+			self.verify = Some(Verify {
+				indic,
+				digests: Digests::new(expected.iter().map(|sum| sum.algorithm)),
+				expected,
+			});
+		}
+		self
+	}
+
+	/// The header of the entry being read.
+	pub fn header(&self) -> &EntryHeader {
+		&self.header
+	}
+
+	/// The length of the entry data as stored, that is, before decoding.
+	pub fn encoded_len(&self) -> u64 {
+		self.encoded_len
+	}
+
+	/// Where the entry data starts in the source.
+	pub(crate) fn data_start(&self) -> u64 {
+		self.data_start
+	}
+}
+
+impl AsyncRead for EntryReader<'_> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut [u8],
+	) -> Poll<Result<usize, Error>> {
+		let read = Pin::new(&mut self.inner).poll_read(cx, buf);
+		if let Poll::Ready(Ok(n)) = read {
+			if n > 0 {
+				if let Some(verify) = &mut self.verify {
+					verify.digests.update(&buf[..n]);
+				}
+			} else if !buf.is_empty() {
+				if let Some(verify) = self.verify.take() {
+					if let Some(algorithm) = verify.digests.check(&verify.expected) {
+						return Poll::Ready(Err(Error::new(
+							ErrorKind::InvalidData,
+							TomoError::ChecksumMismatch {
+								indic: verify.indic,
+								algorithm,
+							},
+						)));
+					}
+				}
+			}
+		}
+		read
+	}
+}
+
+/// Reads the undecoded bytes of an entry, stopping at its end.
+struct RawReader<'a, 's> {
+	source: &'a mut SourceState<'s>,
+	left: u64,
+}
+
+impl AsyncRead for RawReader<'_, '_> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut [u8],
+	) -> Poll<Result<usize, Error>> {
+		if self.left == 0 {
+			return Poll::Ready(Ok(0));
+		}
+
+		let max = self.left.min(buf.len() as u64) as usize;
+		let poll = Pin::new(&mut self.source.source).poll_read(cx, &mut buf[..max]);
+		if let Poll::Ready(Ok(n)) = poll {
+			self.source.offset += n as u64;
+			self.left -= n as u64;
+		}
+		poll
+	}
+}
@@ -0,0 +1,396 @@
+use crate::{
+	parsers::{Attributes, Device, IndicKind, Path, Timestamp},
+	IndexedPath, Resolved, Tomo, TomoError,
+};
+use deku::DekuContainerRead;
+use futures::io::{copy, AllowStdIo, AsyncReadExt};
+use std::{
+	ffi::OsString,
+	fs, io,
+	path::{Path as StdPath, PathBuf},
+};
+
+impl<'s> Tomo<'s> {
+	/// Extract the resolved view of every loaded container into a directory.
+	///
+	/// Creates directories, files, symlinks, hardlinks, devices and named pipes, then restores
+	/// their attributes as far as permitted: ownership and some xattrs usually need privileges,
+	/// and failing to set those is not an error. Sockets are skipped. The directory is created if
+	/// it doesn't exist, and existing files in the way are replaced.
+	///
+	/// Paths are mapped to the filesystem segment by segment, the trailing NUL of each segment
+	/// being dropped. Extraction is refused with [`TomoError::UnsafePath`], before anything is
+	/// written, if a path:
+	/// - has a segment that isn't a name (e.g. the root),
+	/// - has a segment that is empty, `.` or `..`, or contains `/` or NUL (or, on Windows, `\`
+	///   or `:`), or isn't valid UTF-8 on platforms where paths are not bytes,
+	/// - is under another path which isn't a directory (e.g. a file inside a symlink).
+	///
+	/// Symlinks are created last, and extraction stops with [`TomoError::UnsafePath`] if a path
+	/// would be written through a symlink already in the directory. Symlink targets are not
+	/// checked: they're only followed once extraction is over.
+	///
+	/// Entry data is checked against the container's checksums, if it has some, as it's written
+	/// out. Extraction stops with [`TomoError::ChecksumMismatch`] at the first corrupt entry,
+	/// leaving what was written so far. Use [`Tomo::verify`] to check everything beforehand.
+	pub async fn extract_to(&mut self, dir: &StdPath) -> Result<(), TomoError> {
+		let resolved = self.resolve().await?;
+
+		let mut planned = Vec::with_capacity(resolved.len());
+		for item in resolved.iter() {
+			let relative = safe_relative(&item.path)?;
+
+			let mut parent = item.path.parent();
+			while let Some(ancestor) = parent {
+				if let Some(found) = resolved.get(&ancestor) {
+					if found.indic.kind != IndicKind::Dir {
+						return Err(TomoError::UnsafePath(item.path.clone()));
+					}
+				}
+				parent = ancestor.parent();
+			}
+
+			planned.push((item, relative));
+		}
+
+		fs::create_dir_all(dir)?;
+
+		let mut dirs = Vec::new();
+		let mut hardlinks = Vec::new();
+		let mut symlinks = Vec::new();
+		for (item, relative) in planned {
+			let target = prepare(dir, &relative, item)?;
+			match item.indic.kind {
+				IndicKind::Dir => {
+					if !target.is_dir() {
+						fs::create_dir(&target)?;
+					}
+					dirs.push((item, target));
+					continue;
+				}
+				IndicKind::File => self.write_file(item, &target).await?,
+				IndicKind::Hardlink => {
+					hardlinks.push((item, target));
+					continue;
+				}
+				IndicKind::Symlink => {
+					symlinks.push(item);
+					continue;
+				}
+				IndicKind::CharDevice | IndicKind::BlockDevice => {
+					let mut data = Vec::new();
+					self.open_indexed(item)
+						.await?
+						.read_to_end(&mut data)
+						.await
+						.map_err(TomoError::from_io)?;
+					let (_, device) = Device::from_bytes((&data, 0))?;
+					if !make_special(item, Some(device), &target)? {
+						continue;
+					}
+				}
+				IndicKind::Fifo => {
+					if !make_special(item, None, &target)? {
+						continue;
+					}
+				}
+				_ => continue,
+			}
+
+			restore(&target, item, false)?;
+		}
+
+		for (item, target) in hardlinks {
+			self.write_hardlink(&resolved, dir, item, &target).await?;
+		}
+
+		for item in symlinks {
+			let mut link = Vec::new();
+			self.open_indexed(item)
+				.await?
+				.read_to_end(&mut link)
+				.await
+				.map_err(TomoError::from_io)?;
+			let link = os_string(link).ok_or_else(|| TomoError::UnsafePath(item.path.clone()))?;
+
+			// re-check: other symlinks may have been created in the meantime
+			let target = prepare(dir, &safe_relative(&item.path)?, item)?;
+			make_symlink(&link, &target)?;
+			restore(&target, item, true)?;
+		}
+
+		// deepest first, so e.g. read-only directories are only made so once they're filled in
+		for (item, target) in dirs.into_iter().rev() {
+			restore(&target, item, false)?;
+		}
+
+		Ok(())
+	}
+
+	async fn write_file(&mut self, item: &IndexedPath, target: &StdPath) -> Result<(), TomoError> {
+		let mut file = AllowStdIo::new(fs::File::create(target)?);
+		copy(self.open_indexed(item).await?, &mut file)
+			.await
+			.map_err(TomoError::from_io)?;
+		Ok(())
+	}
+
+	/// Hardlink to the target if it was extracted, or otherwise write a copy of its contents.
+	///
+	/// The target might not have been extracted if another container has overridden its path.
+	async fn write_hardlink(
+		&mut self,
+		resolved: &Resolved,
+		dir: &StdPath,
+		item: &IndexedPath,
+		target: &StdPath,
+	) -> Result<(), TomoError> {
+		let linked = self.hardlink_target(item).await?;
+		let extracted = resolved.get(&linked.path).is_some_and(|winner| {
+			(winner.source, winner.container, winner.indic.offset)
+				== (linked.source, linked.container, linked.indic.offset)
+		});
+
+		if extracted && linked.indic.kind != IndicKind::Symlink {
+			fs::hard_link(dir.join(safe_relative(&linked.path)?), target)?;
+		} else if linked.indic.kind == IndicKind::File {
+			self.write_file(&linked, target).await?;
+		} else {
+			return Err(TomoError::MissingLinkTarget);
+		}
+
+		restore(target, item, false)
+	}
+}
+
+/// Map a path to a relative filesystem path, refusing anything that could escape.
+fn safe_relative(path: &Path) -> Result<PathBuf, TomoError> {
+	let unsafe_path = || TomoError::UnsafePath(path.clone());
+	if path.segments().is_empty() {
+		return Err(unsafe_path());
+	}
+
+	let mut relative = PathBuf::new();
+	for seg in path.segments() {
+		let bytes = seg.name().ok_or_else(unsafe_path)?;
+
+		let forbidden: &[u8] = if cfg!(windows) { b"/\0\\:" } else { b"/\0" };
+		if bytes.is_empty()
+			|| bytes == b"."
+			|| bytes == b".."
+			|| bytes.iter().any(|b| forbidden.contains(b))
+		{
+			return Err(unsafe_path());
+		}
+
+		relative.push(os_string(bytes.to_vec()).ok_or_else(unsafe_path)?);
+	}
+
+	Ok(relative)
+}
+
+fn os_string(bytes: Vec<u8>) -> Option<OsString> {
+	#[cfg(unix)]
+	{
+		use std::os::unix::ffi::OsStringExt;
+		Some(OsString::from_vec(bytes))
+	}
+
+	#[cfg(not(unix))]
+	{
+		String::from_utf8(bytes).ok().map(OsString::from)
+	}
+}
+
+/// Make way for a path to be extracted.
+///
+/// Creates missing parent directories, refusing to go through anything that isn't a directory
+/// (including symlinks to directories), and removes whatever non-directory is at the path.
+fn prepare(dir: &StdPath, relative: &StdPath, item: &IndexedPath) -> Result<PathBuf, TomoError> {
+	let unsafe_path = || TomoError::UnsafePath(item.path.clone());
+
+	let mut current = dir.to_path_buf();
+	let mut components = relative.components().peekable();
+	while let Some(component) = components.next() {
+		current.push(component);
+		let last = components.peek().is_none();
+
+		match fs::symlink_metadata(&current) {
+			Ok(meta) if last => {
+				if meta.is_dir() {
+					if item.indic.kind != IndicKind::Dir {
+						return Err(TomoError::Io(io::Error::new(
+							io::ErrorKind::AlreadyExists,
+							"a directory is in the way",
+						)));
+					}
+				} else {
+					fs::remove_file(&current)?;
+				}
+			}
+			Ok(meta) if meta.is_dir() => {}
+			Ok(_) => return Err(unsafe_path()),
+			Err(err) if err.kind() == io::ErrorKind::NotFound => {
+				if !last {
+					fs::create_dir(&current)?;
+				}
+			}
+			Err(err) => return Err(err.into()),
+		}
+	}
+
+	Ok(current)
+}
+
+/// Create a device or named pipe. Returns false if that's not permitted or supported.
+fn make_special(
+	item: &IndexedPath,
+	device: Option<Device>,
+	target: &StdPath,
+) -> Result<bool, TomoError> {
+	#[cfg(unix)]
+	{
+		use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+		let kind = match item.indic.kind {
+			IndicKind::CharDevice => libc::S_IFCHR,
+			IndicKind::BlockDevice => libc::S_IFBLK,
+			_ => libc::S_IFIFO,
+		};
+		let dev = device.map_or(0, |d| libc::makedev(d.major as _, d.minor as _));
+		let path = CString::new(target.as_os_str().as_bytes())
+			.map_err(|_| TomoError::UnsafePath(item.path.clone()))?;
+
+		// SAFETY: the path is a valid NUL-terminated string that outlives the call
+		if unsafe { libc::mknod(path.as_ptr(), kind | 0o600, dev) } == 0 {
+			Ok(true)
+		} else {
+			let err = io::Error::last_os_error();
+			if err.kind() == io::ErrorKind::PermissionDenied {
+				Ok(false)
+			} else {
+				Err(err.into())
+			}
+		}
+	}
+
+	#[cfg(not(unix))]
+	{
+		let _ = (item, device, target);
+		Ok(false)
+	}
+}
+
+fn make_symlink(link: &OsString, target: &StdPath) -> Result<(), TomoError> {
+	#[cfg(unix)]
+	std::os::unix::fs::symlink(link, target)?;
+
+	#[cfg(windows)]
+	std::os::windows::fs::symlink_file(link, target)?;
+
+	#[cfg(not(any(unix, windows)))]
+	let _ = (link, target);
+
+	Ok(())
+}
+
+/// Restore attributes onto an extracted path, skipping what isn't permitted.
+fn restore(target: &StdPath, item: &IndexedPath, symlink: bool) -> Result<(), TomoError> {
+	let attrs = match &item.attrs {
+		Some(attrs) => attrs,
+		None => return Ok(()),
+	};
+
+	let permitted = |res: io::Result<()>| match res {
+		Err(err) if err.kind() == io::ErrorKind::PermissionDenied => Ok(()),
+		other => other,
+	};
+
+	#[cfg(unix)]
+	if attrs.uid.is_some() || attrs.gid.is_some() {
+		permitted(std::os::unix::fs::lchown(target, attrs.uid, attrs.gid))?;
+	}
+
+	attrs.restore_xattrs(target)?;
+
+	if !symlink {
+		// before the mode, which may not let us touch the path anymore
+		if matches!(item.indic.kind, IndicKind::File | IndicKind::Dir) {
+			permitted(set_times(target, attrs))?;
+		}
+		set_mode(target, attrs)?;
+	}
+
+	Ok(())
+}
+
+fn set_mode(target: &StdPath, attrs: &Attributes) -> io::Result<()> {
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		fs::set_permissions(target, fs::Permissions::from_mode(attrs.mode as u32))
+	}
+
+	#[cfg(not(unix))]
+	{
+		let mut permissions = fs::metadata(target)?.permissions();
+		permissions.set_readonly(attrs.mode & 0o222 == 0);
+		fs::set_permissions(target, permissions)
+	}
+}
+
+fn set_times(target: &StdPath, attrs: &Attributes) -> io::Result<()> {
+	if attrs.mtime.is_none() && attrs.atime.is_none() {
+		return Ok(());
+	}
+
+	#[cfg(unix)]
+	{
+		use std::{convert::TryInto, ffi::CString, os::unix::ffi::OsStrExt};
+
+		// times the platform can't represent are left alone
+		let spec = |time: Option<Timestamp>| {
+			time.filter(|time| time.nanos < 1_000_000_000)
+				.and_then(|time| {
+					// time_t is 32 bits on some platforms
+					#[allow(clippy::useless_conversion)]
+					Some(libc::timespec {
+						tv_sec: time.secs.try_into().ok()?,
+						tv_nsec: time.nanos as _,
+					})
+				})
+				.unwrap_or(libc::timespec {
+					tv_sec: 0,
+					tv_nsec: libc::UTIME_OMIT,
+				})
+		};
+		let times = [spec(attrs.atime), spec(attrs.mtime)];
+		let path = CString::new(target.as_os_str().as_bytes())
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+		// SAFETY: the path is a valid NUL-terminated string and times has two entries, both
+		// outliving the call
+		if unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), 0) } == 0 {
+			Ok(())
+		} else {
+			Err(io::Error::last_os_error())
+		}
+	}
+
+	#[cfg(not(unix))]
+	{
+		// times the platform can't represent are left alone
+		let mut times = fs::FileTimes::new();
+		if let Some(mtime) = attrs.mtime.and_then(Timestamp::to_system_time) {
+			times = times.set_modified(mtime);
+		}
+		if let Some(atime) = attrs.atime.and_then(Timestamp::to_system_time) {
+			times = times.set_accessed(atime);
+		}
+
+		fs::File::options()
+			.write(true)
+			.open(target)?
+			.set_times(times)
+	}
+}
@@ -0,0 +1,287 @@
+use crate::{
+	parsers::{Path, PathSeg},
+	TomoError,
+};
+
+/// A glob pattern over [`Path`]s.
+///
+/// Patterns are made of `/`-separated parts, each matched against one segment of a path, with the
+/// trailing NUL of segments left out. Within a part:
+/// - `*` matches any number of bytes,
+/// - `?` matches exactly one byte,
+/// - `[abc]`, `[a-z]` match one byte from a set or range, and `[!abc]` one byte not in it,
+/// - `\` matches the next byte literally.
+///
+/// A part that is exactly `**` matches any number of segments, including none. Patterns are
+/// anchored at both ends: `*.txt` only matches single-segment paths, and `**/*.txt` matches at
+/// any depth. Only [`PathSeg::Segment`]s are matched by parts; other kinds of segments are only
+/// matched by `**`.
+///
+/// Patterns are bytes, so they can match segments that aren't valid UTF-8. To match everything
+/// under a path exactly, whatever its segments, use [`Glob::under`].
+///
+/// ```
+/// use tomo::{Glob, parsers::Path};
+/// let path = Path::from("src/lib.rs");
+///
+/// assert!(Glob::new("src/*.rs").unwrap().matches(&path));
+/// assert!(Glob::new("**/lib.[rs][rs]").unwrap().matches(&path));
+/// assert!(!Glob::new("*.rs").unwrap().matches(&path));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Glob {
+	parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Part {
+	AnyDepth,
+	Segment(Vec<Token>),
+	Exact(PathSeg),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+	Literal(u8),
+	AnyBytes,
+	AnyByte,
+	Class {
+		negated: bool,
+		ranges: Vec<(u8, u8)>,
+	},
+}
+
+impl Glob {
+	/// Parse a pattern.
+	///
+	/// Fails with [`TomoError::InvalidGlob`] if a `[` is never closed or a `\` ends the pattern.
+	pub fn new(pattern: impl AsRef<[u8]>) -> Result<Self, TomoError> {
+		let pattern = pattern.as_ref();
+		let invalid = || TomoError::InvalidGlob(String::from_utf8_lossy(pattern).into_owned());
+
+		let mut parts = Vec::new();
+		for part in pattern.split(|b| *b == b'/') {
+			if part == b"**" {
+				parts.push(Part::AnyDepth);
+				continue;
+			}
+
+			let mut tokens = Vec::new();
+			let mut bytes = part.iter().copied();
+			while let Some(byte) = bytes.next() {
+				tokens.push(match byte {
+					b'*' => Token::AnyBytes,
+					b'?' => Token::AnyByte,
+					b'\\' => Token::Literal(bytes.next().ok_or_else(invalid)?),
+					b'[' => parse_class(&mut bytes).ok_or_else(invalid)?,
+					other => Token::Literal(other),
+				});
+			}
+			parts.push(Part::Segment(tokens));
+		}
+
+		Ok(Self { parts })
+	}
+
+	/// A pattern matching a path and everything under it.
+	///
+	/// Segments of the prefix are matched exactly, including those that aren't names, like
+	/// [`PathSeg::Root`]. This is the same as escaping every segment and appending `/**`.
+	pub fn under(prefix: &Path) -> Self {
+		let mut parts = prefix.iter().cloned().map(Part::Exact).collect::<Vec<_>>();
+		parts.push(Part::AnyDepth);
+		Self { parts }
+	}
+
+	/// Whether the pattern matches the whole path.
+	pub fn matches(&self, path: &Path) -> bool {
+		match_parts(&self.parts, path.segments())
+	}
+
+	/// Whether the pattern could match this path or any path under it.
+	///
+	/// When walking a tree, anything under a path for which this is false can be skipped.
+	pub fn may_match_under(&self, path: &Path) -> bool {
+		match_prefix(&self.parts, path.segments())
+	}
+}
+
+/// Parse a byte class, the opening `[` having been consumed. Returns `None` if it's not closed.
+fn parse_class(bytes: &mut impl Iterator<Item = u8>) -> Option<Token> {
+	let mut negated = false;
+	let mut members = Vec::new();
+	loop {
+		match bytes.next()? {
+			b'!' if members.is_empty() && !negated => negated = true,
+			b']' if !members.is_empty() => break,
+			b'\\' => members.push((bytes.next()?, true)),
+			other => members.push((other, false)),
+		}
+	}
+
+	// `-` is only special between two members
+	let mut ranges = Vec::new();
+	let mut rest = &members[..];
+	while let Some(((start, _), others)) = rest.split_first() {
+		match others {
+			[(b'-', false), (end, _), others @ ..] => {
+				ranges.push((*start, *end));
+				rest = others;
+			}
+			_ => {
+				ranges.push((*start, *start));
+				rest = others;
+			}
+		}
+	}
+
+	Some(Token::Class { negated, ranges })
+}
+
+fn match_parts(parts: &[Part], segments: &[PathSeg]) -> bool {
+	match parts.split_first() {
+		None => segments.is_empty(),
+		Some((Part::AnyDepth, rest)) => {
+			(0..=segments.len()).any(|skip| match_parts(rest, &segments[skip..]))
+		}
+		Some((Part::Segment(tokens), rest)) => match segments.split_first() {
+			Some((segment, others)) => match_segment(tokens, segment) && match_parts(rest, others),
+			None => false,
+		},
+		Some((Part::Exact(exact), rest)) => match segments.split_first() {
+			Some((segment, others)) => segment == exact && match_parts(rest, others),
+			None => false,
+		},
+	}
+}
+
+fn match_prefix(parts: &[Part], segments: &[PathSeg]) -> bool {
+	let (segment, others) = match segments.split_first() {
+		Some(split) => split,
+		None => return true,
+	};
+
+	match parts.split_first() {
+		None => false,
+		Some((Part::AnyDepth, _)) => true,
+		Some((Part::Segment(tokens), rest)) => {
+			match_segment(tokens, segment) && match_prefix(rest, others)
+		}
+		Some((Part::Exact(exact), rest)) => segment == exact && match_prefix(rest, others),
+	}
+}
+
+fn match_segment(tokens: &[Token], segment: &PathSeg) -> bool {
+	segment.name().is_some_and(|name| match_bytes(tokens, name))
+}
+
+fn match_bytes(tokens: &[Token], bytes: &[u8]) -> bool {
+	match tokens.split_first() {
+		None => bytes.is_empty(),
+		Some((Token::AnyBytes, rest)) => {
+			(0..=bytes.len()).any(|skip| match_bytes(rest, &bytes[skip..]))
+		}
+		Some((token, rest)) => match bytes.split_first() {
+			Some((byte, others)) => {
+				let matched = match token {
+					Token::Literal(literal) => literal == byte,
+					Token::AnyByte => true,
+					Token::Class { negated, ranges } => {
+						ranges
+							.iter()
+							.any(|(start, end)| (start..=end).contains(&byte))
+							!= *negated
+					}
+					Token::AnyBytes => unreachable!(),
+				};
+				matched && match_bytes(rest, others)
+			}
+			None => false,
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn matches(pattern: &str, segs: &[&[u8]]) -> bool {
+		Glob::new(pattern).unwrap().matches(&Path::from_names(segs))
+	}
+
+	#[test]
+	fn wildcards() {
+		assert!(matches("a", &[b"a"]));
+		assert!(!matches("a", &[b"a", b"b"]));
+		assert!(matches("*", &[b""]));
+		assert!(matches("*.rs", &[b"lib.rs"]));
+		assert!(!matches("*.rs", &[b"src", b"lib.rs"]));
+		assert!(matches("l?b.rs", &[b"lib.rs"]));
+		assert!(!matches("l?b.rs", &[b"lb.rs"]));
+		assert!(matches("a*b*c", &[b"aXXbYYc"]));
+		assert!(matches("\\*", &[b"*"]));
+		assert!(!matches("\\*", &[b"a"]));
+		assert!(matches("*", &[b"\xFF\xFE"]));
+	}
+
+	#[test]
+	fn any_depth() {
+		assert!(matches("**", &[]));
+		assert!(matches("**", &[b"a", b"b"]));
+		assert!(matches("**/*.rs", &[b"lib.rs"]));
+		assert!(matches("**/*.rs", &[b"src", b"deep", b"lib.rs"]));
+		assert!(matches("src/**", &[b"src"]));
+		assert!(matches("src/**/mod.rs", &[b"src", b"a", b"b", b"mod.rs"]));
+		assert!(!matches("src/**/mod.rs", &[b"lib", b"mod.rs"]));
+	}
+
+	#[test]
+	fn classes() {
+		assert!(matches("[abc]", &[b"b"]));
+		assert!(!matches("[abc]", &[b"d"]));
+		assert!(matches("[a-c]x", &[b"bx"]));
+		assert!(matches("[!a-c]", &[b"d"]));
+		assert!(!matches("[!a-c]", &[b"a"]));
+		assert!(matches("[]]", &[b"]"]));
+		assert!(matches("[a-]", &[b"-"]));
+		assert!(matches("[\\!]", &[b"!"]));
+	}
+
+	#[test]
+	fn prefixes() {
+		let glob = Glob::new("src/*/mod.rs").unwrap();
+		let under = |segs: &[&[u8]]| glob.may_match_under(&Path::from_names(segs));
+		assert!(under(&[]));
+		assert!(under(&[b"src"]));
+		assert!(under(&[b"src", b"deep"]));
+		assert!(under(&[b"src", b"deep", b"mod.rs"]));
+		assert!(!under(&[b"lib"]));
+		assert!(!under(&[b"src", b"deep", b"mod.rs", b"more"]));
+		assert!(Glob::new("src/**")
+			.unwrap()
+			.may_match_under(&Path::from("src/a/b/c")));
+
+		let rooted = Path::from("/etc");
+		let glob = Glob::under(&rooted);
+		assert!(glob.matches(&rooted));
+		assert!(glob.matches(&Path::from("/etc/passwd")));
+		assert!(!glob.matches(&Path::from("etc/passwd")));
+		assert!(!glob.matches(&Path::new(vec![PathSeg::Root])));
+		assert!(glob.may_match_under(&Path::new(vec![PathSeg::Root])));
+		assert!(Glob::under(&Path::from_names(&[b"*"])).matches(&Path::from_names(&[b"*", b"a"])));
+		assert!(!Glob::under(&Path::from_names(&[b"*"])).matches(&Path::from_names(&[b"a"])));
+	}
+
+	#[test]
+	fn invalid() {
+		assert!(Glob::new("[abc").is_err());
+		assert!(Glob::new("abc\\").is_err());
+	}
+
+	#[test]
+	fn other_segments() {
+		let rooted = Path::from("/etc");
+		assert!(Glob::new("**/etc").unwrap().matches(&rooted));
+		assert!(!Glob::new("*/etc").unwrap().matches(&rooted));
+	}
+}
@@ -1,19 +1,42 @@
 use deku::DekuContainerRead;
-use futures::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
-use parsers::{ContainerHeader, CONTAINER_HEADER_SIZE};
+use futures::{stream::StreamExt, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+use lookup::{ChecksumTable, LookupTable, MetaTables};
+use parsers::{
+	Checksum, ContainerHeader, EntryHeader, HardlinkTarget, Indic, IndicKind, Mode, Path,
+	CONTAINER_HEADER_SIZE, INDIC_SIZE,
+};
 use seekable::{Seekable, SeekableSource};
-use std::{fmt, io::SeekFrom};
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt,
+	io::SeekFrom,
+	sync::Arc,
+};
 use thiserror::Error;
 
-pub use stream::PathsStream;
+pub use entry::EntryReader;
+pub use glob::Glob;
+pub use resolve::Resolved;
+pub use stream::{EntriesStream, Entry, IndexedPath, IndexedPathsStream, PathsStream};
+pub use verify::{CorruptEntry, Corruption, Verification};
+pub use writer::{Compression, PackOptions, TomoWriter, DEFAULT_DICTIONARY_THRESHOLD};
 
+mod checksum;
+mod entry;
+mod extract;
+mod glob;
+mod lookup;
 pub mod parsers;
+mod resolve;
 pub mod seekable;
 mod stream;
+mod verify;
+mod writer;
+mod xattrs;
 
 pub mod prelude {
 	pub use crate::seekable::{Seekable, SeekableSource};
-	pub use crate::{SourceStatus, Tomo, TomoError};
+	pub use crate::{EntryReader, SourceStatus, Tomo, TomoError, TomoWriter};
 	pub use futures::stream::StreamExt as _;
 }
 
@@ -22,12 +45,16 @@ pub mod prelude {
 #[derive(Debug, Default)]
 pub struct Tomo<'s> {
 	sources: Vec<SourceState<'s>>,
+	mode_override: Option<Mode>,
 }
 
 pub struct SourceState<'s> {
 	source: Box<dyn SeekableSource + 's>,
 	offset: u64,
 	headers: Vec<(u64, ContainerHeader)>,
+	dictionaries: HashMap<(usize, u64), Arc<Vec<u8>>>,
+	tables: HashMap<usize, MetaTables>,
+	paths_tables: HashMap<usize, LookupTable>,
 }
 
 impl fmt::Debug for SourceState<'_> {
@@ -36,6 +63,9 @@ impl fmt::Debug for SourceState<'_> {
 			.field("stream", &"<boxed async reader>")
 			.field("offset", &self.offset)
 			.field("headers", &self.headers)
+			.field("dictionaries", &self.dictionaries.len())
+			.field("tables", &self.tables.len())
+			.field("paths_tables", &self.paths_tables.len())
 			.finish()
 	}
 }
@@ -46,6 +76,9 @@ impl<'s> SourceState<'s> {
 			source,
 			offset: 0,
 			headers: Vec::new(),
+			dictionaries: HashMap::new(),
+			tables: HashMap::new(),
+			paths_tables: HashMap::new(),
 		}
 	}
 
@@ -54,6 +87,10 @@ impl<'s> SourceState<'s> {
 		self.headers.len()
 	}
 
+	pub fn is_empty(&self) -> bool {
+		self.headers.is_empty()
+	}
+
 	pub(crate) async fn seek_to(&mut self, target: u64) -> Result<(), TomoError> {
 		if target > self.offset {
 			let diff = (target - self.offset) as i64;
@@ -72,25 +109,398 @@ impl<'s> SourceState<'s> {
 
 	pub(crate) async fn read(&mut self, bytes: u64) -> Result<Vec<u8>, TomoError> {
 		let mut buf = vec![0; bytes as usize];
-		let bytes_read = self.source.read(&mut buf).await? as u64;
-		if bytes_read > bytes {
-			panic!("read more bytes than would fit in buffer, somehow");
-		} else if bytes_read < bytes {
+		let mut bytes_read = 0;
+		while bytes_read < buf.len() {
+			match self.source.read(&mut buf[bytes_read..]).await? {
+				0 => break,
+				n => bytes_read += n,
+			}
+		}
+
+		self.offset += bytes_read as u64;
+		if (bytes_read as u64) < bytes {
 			return Err(TomoError::UnexpectedEof {
 				expected: bytes,
-				obtained: bytes_read,
+				obtained: bytes_read as u64,
 			});
 		}
 
 		Ok(buf)
 	}
 
+	/// Where the header of a container starts.
+	pub(crate) fn container_start(&self, container: usize) -> u64 {
+		self.headers[container].0
+	}
+
+	/// Where the index of a container starts.
+	pub(crate) fn index_start(&self, container: usize) -> u64 {
+		self.container_start(container) + (CONTAINER_HEADER_SIZE as u64)
+	}
+
+	/// Where the entries section of a container starts. Indic offsets are relative to this.
+	pub(crate) fn entries_start(&self, container: usize) -> u64 {
+		self.index_start(container) + self.headers[container].1.index_bytes
+	}
+
+	/// Read the header of the entry an indic points to.
+	///
+	/// Returns the header and the (absolute) offset at which the entry's data starts.
+	pub(crate) async fn entry_header(
+		&mut self,
+		container: usize,
+		indic: &Indic,
+	) -> Result<(EntryHeader, u64), TomoError> {
+		let start = self.entries_start(container) + indic.offset;
+		self.seek_to(start).await?;
+
+		let mut bytes = self.read(2).await?;
+		if bytes[0] & 0b1000_0000 != 0 {
+			let params_bytes = self.read(2).await?;
+			let len = u16::from_le_bytes([params_bytes[0], params_bytes[1]]);
+			bytes.extend(params_bytes);
+			bytes.extend(self.read(len as u64).await?);
+		}
+
+		let (_, header) = EntryHeader::from_bytes((&bytes, 0))?;
+		Ok((header, start + bytes.len() as u64))
+	}
+
+	/// Read an indic from a container's index, by its (0-indexed) position.
+	///
+	/// Returns `None` past the end of the index, including for positions so large (e.g. from a
+	/// corrupt entry) that their offset overflows.
+	pub(crate) async fn indic(
+		&mut self,
+		container: usize,
+		n: u64,
+	) -> Result<Option<Indic>, TomoError> {
+		let position = match n.checked_mul(INDIC_SIZE) {
+			Some(position) if position.checked_add(INDIC_SIZE).is_some() => position,
+			_ => return Ok(None),
+		};
+		if position + INDIC_SIZE > self.headers[container].1.index_bytes {
+			return Ok(None);
+		}
+
+		self.seek_to(self.index_start(container) + position).await?;
+		let buf = self.read(INDIC_SIZE).await?;
+		let (_, indic) = Indic::from_bytes((&buf, 0))?;
+		Ok(Some(indic))
+	}
+
+	/// Load a zstd dictionary, by the (0-indexed) number of the indic holding it.
+	///
+	/// Dictionaries are cached, so each is only read once per container.
+	pub(crate) async fn dictionary(
+		&mut self,
+		container: usize,
+		n: u64,
+	) -> Result<Arc<Vec<u8>>, TomoError> {
+		if let Some(dict) = self.dictionaries.get(&(container, n)) {
+			return Ok(dict.clone());
+		}
+
+		let indic = match self.indic(container, n).await? {
+			Some(indic) => indic,
+			None => return Err(TomoError::MissingDictionary(n)),
+		};
+
+		let mut dict = Vec::new();
+		EntryReader::without_dictionary(self, container, &indic)
+			.await?
+			.read_to_end(&mut dict)
+			.await?;
+
+		let dict = Arc::new(dict);
+		self.dictionaries.insert((container, n), dict.clone());
+		Ok(dict)
+	}
+
+	/// The Attributes and ReversePaths tables of a container, and its Paths indic.
+	///
+	/// These are cached, so they're only read once per container.
+	pub(crate) async fn tables(&mut self, container: usize) -> Result<MetaTables, TomoError> {
+		if let Some(tables) = self.tables.get(&container) {
+			return Ok(tables.clone());
+		}
+
+		let tables = MetaTables::read(self, container).await?;
+		self.tables.insert(container, tables.clone());
+		Ok(tables)
+	}
+
+	/// The Paths table of a container, if it has one.
+	///
+	/// Only read when first needed, and then cached like [`SourceState::tables`].
+	pub(crate) async fn paths_table(
+		&mut self,
+		container: usize,
+	) -> Result<Option<LookupTable>, TomoError> {
+		if let Some(table) = self.paths_tables.get(&container) {
+			return Ok(Some(table.clone()));
+		}
+
+		let indic = match self.tables(container).await?.paths {
+			Some(indic) => indic,
+			None => return Ok(None),
+		};
+		let table = LookupTable::read(self, container, &indic).await?;
+		self.paths_tables.insert(container, table.clone());
+		Ok(Some(table))
+	}
+
+	/// The catting mode of a container.
+	pub(crate) fn mode(&self, container: usize) -> Mode {
+		self.headers[container].1.mode
+	}
+
+	/// Find every indic in a container which has a particular path, with their attributes.
+	///
+	/// Uses the ReversePaths entry if there is one, and otherwise goes through the Paths entry and
+	/// the whole index. Returned in index order. The [`IndexedPath`]s have their `source` set to
+	/// zero, as that's unknown from here.
+	pub(crate) async fn find_indexed(
+		&mut self,
+		container: usize,
+		path: &Path,
+	) -> Result<Vec<IndexedPath>, TomoError> {
+		let tables = self.tables(container).await?;
+		let mut found = Vec::new();
+		if let Some(reverse) = &tables.reverse {
+			for n in reverse.find(self, path).await?.unwrap_or_default() {
+				match self.indic(container, n).await? {
+					Some(indic) => found.push((n, indic)),
+					None => return Err(TomoError::MissingIndic(n)),
+				}
+			}
+		} else {
+			let number = match self.paths_table(container).await? {
+				Some(paths) => match paths.find_path(self, path).await? {
+					Some(n) => n,
+					None => return Ok(Vec::new()),
+				},
+				None => return Ok(Vec::new()),
+			};
+
+			let mut n = 0;
+			while let Some(indic) = self.indic(container, n).await? {
+				if indic.path == number {
+					found.push((n, indic));
+				}
+				n += 1;
+			}
+		}
+
+		let mut indexed = Vec::with_capacity(found.len());
+		for (n, indic) in found {
+			indexed.push(
+				self.with_attributes(&tables, container, n, indic, path.clone())
+					.await?,
+			);
+		}
+
+		Ok(indexed)
+	}
+
+	/// Find every indic in a container which has a path, and in an Overlay container, also those
+	/// which have one of its ancestors, as markers there can hide it.
+	///
+	/// Returned ancestors first, from the top level down. As for [`SourceState::find_indexed`],
+	/// the `source` of the [`IndexedPath`]s is set to zero.
+	async fn find_with_ancestors(
+		&mut self,
+		container: usize,
+		path: &Path,
+		mode: Mode,
+	) -> Result<Vec<IndexedPath>, TomoError> {
+		let mut found = Vec::new();
+		if mode == Mode::Overlay {
+			let mut ancestors = Vec::new();
+			let mut parent = path.parent();
+			while let Some(ancestor) = parent {
+				parent = ancestor.parent();
+				ancestors.push(ancestor);
+			}
+
+			for ancestor in ancestors.iter().rev() {
+				found.extend(self.find_indexed(container, ancestor).await?);
+			}
+		}
+
+		found.extend(self.find_indexed(container, path).await?);
+		Ok(found)
+	}
+
+	/// Find every indic in a container which has a path matching a glob. With `ancestors`, also
+	/// find those which have a path that a match could be under.
+	///
+	/// Uses the ReversePaths entry if there is one, only walking down the branches of the tree
+	/// where the glob could match, and otherwise goes through the whole Paths entry and the whole
+	/// index. Returned in path order, then index order. As for [`SourceState::find_indexed`], the
+	/// `source` of the [`IndexedPath`]s is set to zero.
+	pub(crate) async fn find_matching_indexed(
+		&mut self,
+		container: usize,
+		glob: &Glob,
+		ancestors: bool,
+	) -> Result<Vec<IndexedPath>, TomoError> {
+		let tables = self.tables(container).await?;
+		let mut found = Vec::new();
+		if let Some(reverse) = &tables.reverse {
+			for (path, positions) in reverse.matching(self, glob, ancestors).await? {
+				for n in positions {
+					match self.indic(container, n).await? {
+						Some(indic) => found.push((n, indic, path.clone())),
+						None => return Err(TomoError::MissingIndic(n)),
+					}
+				}
+			}
+		} else {
+			found = self
+				.scan_indexed(container, |path| {
+					glob.matches(path) || (ancestors && glob.may_match_under(path))
+				})
+				.await?;
+		}
+
+		let mut indexed = Vec::with_capacity(found.len());
+		for (n, indic, path) in found {
+			indexed.push(
+				self.with_attributes(&tables, container, n, indic, path)
+					.await?,
+			);
+		}
+
+		Ok(indexed)
+	}
+
+	/// Find every indic in a container which has a path passing a filter, by going through the
+	/// whole Paths entry and then the whole index. Returned in path order, then index order.
+	async fn scan_indexed(
+		&mut self,
+		container: usize,
+		filter: impl Fn(&Path) -> bool,
+	) -> Result<Vec<(u64, Indic, Path)>, TomoError> {
+		let mut paths = HashMap::new();
+		if let Some(table) = self.paths_table(container).await? {
+			for n in 1..=table.count {
+				let path = table.path(self, n).await?;
+				if filter(&path) {
+					paths.insert(n, path);
+				}
+			}
+		}
+
+		let mut found = Vec::new();
+		if paths.is_empty() {
+			return Ok(found);
+		}
+
+		let mut n = 0;
+		while let Some(indic) = self.indic(container, n).await? {
+			if let Some(path) = paths.get(&indic.path) {
+				found.push((n, indic, path.clone()));
+			}
+			n += 1;
+		}
+
+		found.sort_by(|(_, _, a), (_, _, b)| a.cmp(b));
+		Ok(found)
+	}
+
+	async fn with_attributes(
+		&mut self,
+		tables: &MetaTables,
+		container: usize,
+		position: u64,
+		indic: Indic,
+		path: Path,
+	) -> Result<IndexedPath, TomoError> {
+		let attrs = match (indic.attrs, &tables.attrs) {
+			(0, _) => None,
+			(n, Some(table)) => Some(table.attributes(self, n).await?),
+			(n, None) => return Err(TomoError::MissingItem(n)),
+		};
+
+		Ok(IndexedPath {
+			source: 0,
+			container,
+			position,
+			indic,
+			path,
+			attrs,
+		})
+	}
+
+	/// Read an indic by its (0-indexed) position, along with its path and attributes.
+	///
+	/// The returned [`IndexedPath`] has its `source` set to zero, as that's unknown from here.
+	pub(crate) async fn indexed(
+		&mut self,
+		container: usize,
+		n: u64,
+	) -> Result<Option<IndexedPath>, TomoError> {
+		let indic = match self.indic(container, n).await? {
+			Some(indic) => indic,
+			None => return Ok(None),
+		};
+
+		if indic.path == 0 {
+			return Ok(None);
+		}
+
+		let path = match self.paths_table(container).await? {
+			Some(table) => table.path(self, indic.path).await?,
+			None => return Err(TomoError::MissingItem(indic.path)),
+		};
+		let tables = self.tables(container).await?;
+		Ok(Some(
+			self.with_attributes(&tables, container, n, indic, path)
+				.await?,
+		))
+	}
+
+	/// The checksums of an indic, by its (0-indexed) position.
+	///
+	/// Empty if the container has no Checksums entry, or it doesn't cover that indic.
+	pub(crate) async fn checksums(
+		&mut self,
+		container: usize,
+		indic: u64,
+	) -> Result<Vec<Checksum>, TomoError> {
+		match ChecksumTable::find_in(self, container).await? {
+			Some(table) => table.find(self, indic).await,
+			None => Ok(Vec::new()),
+		}
+	}
+
+	/// Find the indic a Hardlink indic points to.
+	pub(crate) async fn hardlink_target(
+		&mut self,
+		container: usize,
+		indic: &Indic,
+	) -> Result<IndexedPath, TomoError> {
+		let mut data = Vec::new();
+		EntryReader::new(self, container, indic)
+			.await?
+			.read_to_end(&mut data)
+			.await?;
+		let (_, target) = HardlinkTarget::from_bytes((&data, 0))?;
+
+		match self.indexed(container, target.indic).await? {
+			Some(target) if target.indic.kind != IndicKind::Hardlink => Ok(target),
+			_ => Err(TomoError::MissingLinkTarget),
+		}
+	}
+
 	/// Load the next container from this source.
 	///
 	/// Seeks to the end of the last known container on the source (or nowhere if none have been
-	/// loaded yet), then attempts to load a container. If it finds one, it will also probe the
-	/// source and return a [`SourceState`] describing whether the source is at its end, or whether
-	/// there's more data to go.
+	/// loaded yet), then attempts to load a container, checking its Integrity entry if it has one
+	/// (see [`Tomo::load`]). If it finds a container, it will also probe the source and return a
+	/// [`SourceState`] describing whether the source is at its end, or whether there's more data
+	/// to go.
 	pub async fn load_next_container(&mut self) -> Result<SourceStatus, TomoError> {
 		let current_end = self
 			.headers
@@ -100,18 +510,20 @@ impl<'s> SourceState<'s> {
 			})
 			.unwrap_or(0);
 
-		self.source
-			.seek(SeekFrom::Current((current_end - self.offset) as i64))
-			.await?;
-
-		let mut buf = vec![0_u8; CONTAINER_HEADER_SIZE];
-		self.offset += self.source.read(&mut buf).await? as u64;
+		self.seek_to(current_end).await?;
+		let buf = self.read(CONTAINER_HEADER_SIZE as u64).await?;
 
 		let (_, header) = ContainerHeader::from_bytes((&buf, 0))?;
-		let end = header.index_bytes + header.entries_bytes;
+		let end = current_end
+			+ (CONTAINER_HEADER_SIZE as u64)
+			+ header.index_bytes
+			+ header.entries_bytes;
 		self.headers.push((current_end, header));
-		self.offset += end as u64;
-		self.source.seek(SeekFrom::Current(end as i64)).await?;
+		if let Err(err) = self.check_integrity(self.headers.len() - 1).await {
+			self.headers.pop();
+			return Err(err);
+		}
+		self.seek_to(end).await?;
 
 		// As per AsyncSeek documentation:
 		//
@@ -137,10 +549,7 @@ impl<'s> SourceState<'s> {
 		})
 	}
 
-	pub(crate) fn index<'src: 's>(
-		&'src mut self,
-		container: usize,
-	) -> Option<stream::IndexStream<'src>> {
+	pub(crate) fn index(&mut self, container: usize) -> Option<stream::IndexStream<'_, 's>> {
 		if container >= self.headers.len() {
 			None
 		} else {
@@ -148,18 +557,31 @@ impl<'s> SourceState<'s> {
 		}
 	}
 
-	// pub(crate) fn entries(&self, container: usize) -> stream::EntriesStream<'_> {
-	// 	stream::EntriesStream::new(self, container)
-	// }
+	/// Go through every entry in a container.
+	///
+	/// Returns `None` if the container isn't loaded.
+	pub fn entries(&mut self, container: usize) -> Option<EntriesStream<'_, 's>> {
+		if container >= self.headers.len() {
+			None
+		} else {
+			Some(EntriesStream::new(self, container))
+		}
+	}
 }
 
 impl<'s> Tomo<'s> {
 	/// Load one or more containers from a byte source.
 	///
-	/// Reads a seekable byte source into the Tomo state. Only parses the bare minimum it requires
+	/// Reads a seekable byte source into the Tomo state. Parses the header of each container
 	/// upfront, and will seek through on demand for further operations, thus it takes an exclusive
 	/// borrow on the source.
 	///
+	/// Containers with an Integrity entry are also checked as they're loaded, which reads and
+	/// digests their whole index and metadata entries (but not file data), and loading stops with
+	/// a [`TomoError::CorruptContainer`] error at the first one that doesn't check out. Containers
+	/// written with [`TomoWriter::set_integrity`] set to `None` don't have one, so they're loaded
+	/// without that check.
+	///
 	/// The source will be parsed as far as it can, and any container headers found added to the
 	/// state. This may pause indefinitely if the source is waiting for more data and none is
 	/// forthcoming (e.g. a stalled network fetch). It's therefore recommended to preprocess a
@@ -207,7 +629,8 @@ impl<'s> Tomo<'s> {
 	/// Load one container from a byte source.
 	///
 	/// Same as [`Tomo::load`], but stops after a reading a single container. Seeks the source to
-	/// the end of the container, but does not parse more than the header upfront.
+	/// the end of the container, and only parses its header upfront, plus its index and metadata
+	/// entries if it has an Integrity entry to check them against (see [`Tomo::load`]).
 	///
 	/// Returns a shared borrow to the source state created for this source, which can be used to
 	/// prompt the state to load another container or extract data from this particular source, and
@@ -221,11 +644,28 @@ impl<'s> Tomo<'s> {
 		Ok((ss, st))
 	}
 
+	/// Force a catting mode for every container, instead of the modes in their headers.
+	///
+	/// This applies to path resolution (see [`Resolved`]) for all containers, whether they're
+	/// loaded before or after this is called. `None` goes back to using the headers' modes.
+	pub fn set_mode_override(&mut self, mode: Option<Mode>) {
+		self.mode_override = mode;
+	}
+
+	/// The catting mode forced with [`Tomo::set_mode_override`], if any.
+	pub fn mode_override(&self) -> Option<Mode> {
+		self.mode_override
+	}
+
 	/// The amount of loaded containers.
 	pub fn len(&self) -> usize {
 		self.sources.iter().map(|source| source.len()).sum()
 	}
 
+	pub fn is_empty(&self) -> bool {
+		self.sources.iter().all(|source| source.is_empty())
+	}
+
         /// Stream every path in every container.
         ///
         /// Reads every [`parsers::Path`] from the Paths entry in every container for every
@@ -239,16 +679,245 @@ impl<'s> Tomo<'s> {
         /// Stream paths corresponding to every indic in every container.
         ///
         /// Reads every [`parsers::Indic`] from every index in every container and, for those that
-        /// do have a path, reads that path from the container's Paths entry. Stream order is
-        /// unspecified.
+        /// do have a path, reads that path from the container's Paths entry. This includes markers
+        /// like [`IndicKind::Whiteout`], not just files and directories. Stream order is unspecified.
         ///
         /// This may return a different amount of paths than [`Tomo::all_paths`] for two reasons:
         /// 1. paths in the entry that are not referenced in the index (though that's against spec),
         /// 2. several indics can reference the same path (and this stream does not dedupe).
         ///
+        /// Each item also carries the indic's [`parsers::Attributes`], if it has some.
+        ///
         /// Also see [`Tomo::all_paths`].
-	pub fn indexed_paths<'tomo>(&'tomo mut self) -> PathsStream<'tomo, 's> {
-		todo!()
+	pub fn indexed_paths<'tomo>(&'tomo mut self) -> IndexedPathsStream<'tomo, 's> {
+		IndexedPathsStream::new(self)
+	}
+
+	/// Resolve every path across all loaded containers.
+	///
+	/// Reads through every index (as [`Tomo::indexed_paths`] does) and applies the catting mode
+	/// of each container, so that only the winning indic remains for each path. See [`Resolved`]
+	/// for the rules.
+	pub async fn resolve(&mut self) -> Result<Resolved, TomoError> {
+		let modes = self.modes();
+		let mut resolved = Resolved::default();
+		let mut paths = self.indexed_paths();
+		while let Some(candidate) = paths.next().await {
+			let candidate = candidate?;
+			resolved.offer(modes[candidate.source][candidate.container], candidate);
+		}
+
+		Ok(resolved)
+	}
+
+	/// Resolve a single path across all loaded containers.
+	///
+	/// This applies the same rules as [`Tomo::resolve`], but only looks up that one path (and,
+	/// in Overlay containers, its ancestors) in each container instead of reading every index in
+	/// full.
+	pub async fn resolve_path(&mut self, path: &Path) -> Result<Option<IndexedPath>, TomoError> {
+		let mode_override = self.mode_override;
+		let mut resolved = Resolved::default();
+		for (s, source) in self.sources.iter_mut().enumerate() {
+			for container in 0..source.len() {
+				let mode = mode_override.unwrap_or_else(|| source.mode(container));
+				for mut candidate in source.find_with_ancestors(container, path, mode).await? {
+					candidate.source = s;
+					resolved.offer(mode, candidate);
+				}
+			}
+		}
+
+		Ok(resolved.get(path).cloned())
+	}
+
+	/// List the immediate children of a directory across all loaded containers.
+	///
+	/// Applies the same rules as [`Tomo::resolve`], so each child is listed once, as its winning
+	/// indic, and children hidden by markers in Overlay containers are left out. Only looks up that
+	/// directory and everything under it (and, in Overlay containers, its ancestors) in each
+	/// container, using ReversePaths entries where present. Returned in path order.
+	///
+	/// The empty path lists the top level. A child which has no indic of its own, but has paths
+	/// under it (e.g. `b` in a container with `a/b/c` but no `a/b`), is listed as a directory, as
+	/// [`Tomo::extract_to`] would create it: its indic is a Dir with no path, attributes, or entry
+	/// data, and its `container` and `position` are those of the first path under it. Listing a
+	/// path which resolves to something other than a directory, e.g. because a later container
+	/// has a file there, gives nothing.
+	pub async fn read_dir(&mut self, dir: &Path) -> Result<Vec<IndexedPath>, TomoError> {
+		let under = Glob::under(dir);
+		let mode_override = self.mode_override;
+		let mut resolved = Resolved::default();
+		for (s, source) in self.sources.iter_mut().enumerate() {
+			for container in 0..source.len() {
+				let mode = mode_override.unwrap_or_else(|| source.mode(container));
+				let mut candidates = source.find_with_ancestors(container, dir, mode).await?;
+				for candidate in source
+					.find_matching_indexed(container, &under, false)
+					.await?
+				{
+					if &candidate.path != dir {
+						candidates.push(candidate);
+					}
+				}
+
+				for mut candidate in candidates {
+					candidate.source = s;
+					resolved.offer(mode, candidate);
+				}
+			}
+		}
+
+		if let Some(found) = resolved.get(dir) {
+			if found.indic.kind != IndicKind::Dir {
+				return Ok(Vec::new());
+			}
+		}
+
+		let depth = dir.segments().len() + 1;
+		let mut children = BTreeMap::new();
+		for item in resolved.iter() {
+			if item.path.segments().len() < depth || !item.path.starts_with(dir) {
+				continue;
+			}
+
+			let child = Path::new(item.path.segments()[..depth].to_vec());
+			if item.path == child {
+				children.insert(child, item.clone());
+			} else {
+				children
+					.entry(child.clone())
+					.or_insert_with(|| IndexedPath {
+						indic: Indic::new(IndicKind::Dir, 0, 0, 0, 0),
+						path: child,
+						attrs: None,
+						..item.clone()
+					});
+			}
+		}
+
+		Ok(children.into_values().collect())
+	}
+
+	/// Resolve every path matching a glob across all loaded containers.
+	///
+	/// This applies the same rules as [`Tomo::resolve`], as if the result was then filtered by
+	/// [`Glob::matches`], but only looks up matching paths (and, in Overlay containers, the paths
+	/// that matches could be under) in each container. Where a container has a ReversePaths entry,
+	/// only the branches of its tree where the glob could match are read; otherwise its Paths
+	/// entry and index are gone through once. Use [`Glob::under`] to get everything under a path.
+	pub async fn resolve_glob(&mut self, glob: &Glob) -> Result<Resolved, TomoError> {
+		let mode_override = self.mode_override;
+		let mut resolved = Resolved::default();
+		for (s, source) in self.sources.iter_mut().enumerate() {
+			for container in 0..source.len() {
+				let mode = mode_override.unwrap_or_else(|| source.mode(container));
+				let candidates = source
+					.find_matching_indexed(container, glob, mode == Mode::Overlay)
+					.await?;
+
+				for mut candidate in candidates {
+					candidate.source = s;
+					resolved.offer(mode, candidate);
+				}
+			}
+		}
+
+		resolved.retain(|path| glob.matches(path));
+		Ok(resolved)
+	}
+
+	/// Open a file for reading, by path.
+	///
+	/// Finds the indic that wins for this path across all loaded containers (see
+	/// [`Tomo::resolve_path`]), follows it if it's a hardlink, then seeks straight to its entry,
+	/// without reading anything in between.
+	///
+	/// Returns `None` if the path isn't in any container, and [`TomoError::NotAFile`] if the
+	/// winning indic for that path isn't a file. The data is checked as for
+	/// [`Tomo::open_indexed`].
+	pub async fn open(&mut self, path: &Path) -> Result<Option<EntryReader<'_>>, TomoError> {
+		let mut found = match self.resolve_path(path).await? {
+			Some(found) => found,
+			None => return Ok(None),
+		};
+
+		if found.indic.kind == IndicKind::Hardlink {
+			found = self.hardlink_target(&found).await?;
+		}
+
+		if found.indic.kind != IndicKind::File {
+			return Err(TomoError::NotAFile(found.indic.kind));
+		}
+
+		self.open_indexed(&found).await.map(Some)
+	}
+
+	/// Open the entry data of any indic, as found by e.g. [`Tomo::resolve`].
+	///
+	/// Unlike [`Tomo::open`], this works for every kind of indic, and doesn't follow hardlinks.
+	/// For example, reading a Symlink indic's entry gives the link target, and a CharDevice's
+	/// gives a [`parsers::Device`].
+	///
+	/// If the container has checksums for the indic, the data is checked against them as it's
+	/// read: once the end is reached, reading fails with an [`std::io::ErrorKind::InvalidData`]
+	/// error wrapping a [`TomoError::ChecksumMismatch`] if it's corrupt.
+	pub async fn open_indexed(&mut self, item: &IndexedPath) -> Result<EntryReader<'_>, TomoError> {
+		let source = &mut self.sources[item.source];
+		let checksums = source.checksums(item.container, item.position).await?;
+		Ok(EntryReader::new(source, item.container, &item.indic)
+			.await?
+			.verify(item.position, checksums))
+	}
+
+	/// Find what a Hardlink indic links to.
+	///
+	/// Fails with [`TomoError::MissingLinkTarget`] if the target isn't in the index, or is
+	/// itself a hardlink.
+	pub async fn hardlink_target(&mut self, item: &IndexedPath) -> Result<IndexedPath, TomoError> {
+		let source = &mut self.sources[item.source];
+		let mut target = source.hardlink_target(item.container, &item.indic).await?;
+		target.source = item.source;
+		Ok(target)
+	}
+
+	/// Read the target of a symlink, by path.
+	///
+	/// Returns `None` if the path isn't in any container, and [`TomoError::NotASymlink`] if the
+	/// winning indic for that path isn't a symlink.
+	pub async fn read_link(&mut self, path: &Path) -> Result<Option<Vec<u8>>, TomoError> {
+		let found = match self.resolve_path(path).await? {
+			Some(found) => found,
+			None => return Ok(None),
+		};
+
+		if found.indic.kind != IndicKind::Symlink {
+			return Err(TomoError::NotASymlink(found.indic.kind));
+		}
+
+		let mut target = Vec::new();
+		self.open_indexed(&found)
+			.await?
+			.read_to_end(&mut target)
+			.await?;
+		Ok(Some(target))
+	}
+
+	/// The effective catting mode of every loaded container, by source then container.
+	fn modes(&self) -> Vec<Vec<Mode>> {
+		self.sources
+			.iter()
+			.map(|source| {
+				(0..source.len())
+					.map(|c| self.mode_override.unwrap_or_else(|| source.mode(c)))
+					.collect()
+			})
+			.collect()
+	}
+
+	/// Get the state for a loaded source, by the order it was loaded in.
+	pub fn source_mut(&mut self, n: usize) -> Option<&mut SourceState<'s>> {
+		self.sources.get_mut(n)
 	}
 
 	fn add_source<'slf, T: AsyncRead + AsyncSeek + Unpin>(
@@ -275,6 +944,66 @@ pub enum TomoError {
 
 	#[error("tried to read {expected:} bytes and got {obtained:} bytes (unexpected EOF)")]
 	UnexpectedEof { expected: u64, obtained: u64 },
+
+	#[error("entries encoded with {0:?} are not supported")]
+	UnsupportedEncoding(parsers::Encoding),
+
+	#[error("zstd dictionary at indic {0:} is missing or itself uses a dictionary")]
+	MissingDictionary(u64),
+
+	#[error("item {0:} is missing from lookup table")]
+	MissingItem(u32),
+
+	#[error("indic {0:} is missing from the index")]
+	MissingIndic(u64),
+
+	#[error("expected a file, found {0:?}")]
+	NotAFile(parsers::IndicKind),
+
+	#[error("too many paths or attributes for a single container (16 million max)")]
+	ContainerFull,
+
+	#[error("hardlink target is not in the container")]
+	MissingLinkTarget,
+
+	#[error("expected a symlink, found {0:?}")]
+	NotASymlink(parsers::IndicKind),
+
+	#[error("path {0:?} has a segment that can't be stored, e.g. a name containing NUL")]
+	InvalidPath(parsers::Path),
+
+	#[error("refusing to extract unsafe path {0:?}")]
+	UnsafePath(parsers::Path),
+
+	#[error("path is not valid unicode: {0:?}")]
+	NonUnicodePath(std::path::PathBuf),
+
+	#[error("invalid glob pattern: {0}")]
+	InvalidGlob(String),
+
+	#[error("container at offset {offset:} failed its integrity check")]
+	CorruptContainer { offset: u64 },
+
+	#[error("entry data of indic {indic:} doesn't match its {algorithm:?} checksum")]
+	ChecksumMismatch {
+		indic: u64,
+		algorithm: parsers::ChecksumAlgorithm,
+	},
+}
+
+impl TomoError {
+	/// Unwrap errors raised from within readers, like [`TomoError::ChecksumMismatch`], which
+	/// have to go through [`std::io::Error`].
+	pub(crate) fn from_io(err: std::io::Error) -> Self {
+		if !err.get_ref().is_some_and(|inner| inner.is::<TomoError>()) {
+			return Self::Io(err);
+		}
+
+		match err.into_inner().map(|inner| inner.downcast::<TomoError>()) {
+			Some(Ok(inner)) => *inner,
+			_ => unreachable!("checked above"),
+		}
+	}
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -0,0 +1,496 @@
+use crate::{
+	entry::EntryReader,
+	parsers::{
+		Attributes, Checksum, Encoding, Indic, IndicKind, Lookup, Path, PathSeg, PathsEntryHeader,
+		CHECKSUM_SIZE, LOOKUP_SIZE,
+	},
+	Glob, SourceState, TomoError,
+};
+use deku::prelude::*;
+use futures::AsyncReadExt;
+use std::{cmp::Ordering, collections::HashSet, sync::Arc};
+
+/// Random access into the data of an entry.
+///
+/// Raw entries are read from the source as needed. Encoded entries can't be seeked into, so they
+/// are decoded into memory upfront.
+#[derive(Clone, Debug)]
+pub(crate) struct EntryData {
+	data: TableData,
+	len: u64,
+}
+
+#[derive(Clone, Debug)]
+enum TableData {
+	/// Absolute offset of the start of the data in the source.
+	Raw(u64),
+	Decoded(Arc<Vec<u8>>),
+}
+
+impl EntryData {
+	pub async fn read(
+		source: &mut SourceState<'_>,
+		container: usize,
+		indic: &Indic,
+	) -> Result<Self, TomoError> {
+		let mut reader = EntryReader::new(source, container, indic).await?;
+		Ok(if reader.header().encoding() == Encoding::Raw {
+			Self {
+				data: TableData::Raw(reader.data_start()),
+				len: reader.encoded_len(),
+			}
+		} else {
+			let mut decoded = Vec::new();
+			reader.read_to_end(&mut decoded).await?;
+			Self {
+				len: decoded.len() as u64,
+				data: TableData::Decoded(Arc::new(decoded)),
+			}
+		})
+	}
+
+	/// Read bytes from the entry data, `start` being relative to the start of the data.
+	pub async fn bytes(
+		&self,
+		source: &mut SourceState<'_>,
+		start: u64,
+		len: u64,
+	) -> Result<Vec<u8>, TomoError> {
+		let end = start.saturating_add(len);
+		if end > self.len {
+			return Err(TomoError::UnexpectedEof {
+				expected: len,
+				obtained: self.len.saturating_sub(start),
+			});
+		}
+
+		match &self.data {
+			TableData::Raw(base) => {
+				source.seek_to(base + start).await?;
+				source.read(len).await
+			}
+			TableData::Decoded(data) => Ok(data[(start as usize)..(end as usize)].to_vec()),
+		}
+	}
+
+	async fn u32(&self, source: &mut SourceState<'_>, start: u64) -> Result<u32, TomoError> {
+		let bytes = self.bytes(source, start, 4).await?;
+		Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+	}
+
+	async fn u64(&self, source: &mut SourceState<'_>, start: u64) -> Result<u64, TomoError> {
+		let bytes = self.bytes(source, start, 8).await?;
+		let mut buf = [0; 8];
+		buf.copy_from_slice(&bytes);
+		Ok(u64::from_le_bytes(buf))
+	}
+}
+
+/// Random access into an entry made of a count, a lookup table, and items (Paths, Attributes).
+#[derive(Clone, Debug)]
+pub(crate) struct LookupTable {
+	pub count: u32,
+	data: EntryData,
+}
+
+impl LookupTable {
+	pub async fn read(
+		source: &mut SourceState<'_>,
+		container: usize,
+		indic: &Indic,
+	) -> Result<Self, TomoError> {
+		let data = EntryData::read(source, container, indic).await?;
+		let bytes = data.bytes(source, 0, 4).await?;
+		let (_, paths_header) = PathsEntryHeader::from_bytes((&bytes, 0))?;
+		Ok(Self {
+			count: paths_header.path_count as u32,
+			data,
+		})
+	}
+
+	async fn bytes(
+		&self,
+		source: &mut SourceState<'_>,
+		start: u64,
+		len: u64,
+	) -> Result<Vec<u8>, TomoError> {
+		self.data.bytes(source, start, len).await
+	}
+
+	fn items_start(&self) -> u64 {
+		4 + (self.count as u64) * (LOOKUP_SIZE as u64)
+	}
+
+	/// Where an item starts, from its (untrusted) offset in the lookup table.
+	fn item_offset(&self, offset: u64) -> Result<u64, TomoError> {
+		self.items_start().checked_add(offset).ok_or_else(|| {
+			TomoError::Parse(DekuError::Parse(format!(
+				"lookup offset {} is out of range",
+				offset
+			)))
+		})
+	}
+
+	async fn read_lookup(
+		&self,
+		source: &mut SourceState<'_>,
+		position: u32,
+	) -> Result<Lookup, TomoError> {
+		let start = 4 + (position as u64) * (LOOKUP_SIZE as u64);
+		let bytes = self.bytes(source, start, LOOKUP_SIZE as u64).await?;
+		let (_, lookup) = Lookup::from_bytes((&bytes, 0))?;
+		Ok(lookup)
+	}
+
+	/// Read the raw bytes of an item, by its (1-indexed) number.
+	pub async fn item(&self, source: &mut SourceState<'_>, n: u32) -> Result<Vec<u8>, TomoError> {
+		if n == 0 || n > self.count {
+			return Err(TomoError::MissingItem(n));
+		}
+
+		let lookup = self.read_lookup(source, n - 1).await?;
+		if lookup.index != n {
+			return Err(TomoError::MissingItem(n));
+		}
+
+		let start = self.item_offset(lookup.offset)?;
+		let end = if n == self.count {
+			self.data.len
+		} else {
+			self.item_offset(self.read_lookup(source, n).await?.offset)?
+		};
+
+		self.bytes(source, start, end.saturating_sub(start)).await
+	}
+
+	pub async fn path(&self, source: &mut SourceState<'_>, n: u32) -> Result<Path, TomoError> {
+		let bytes = self.item(source, n).await?;
+		let (_, path) = Path::from_bytes((&bytes, 0))?;
+		Ok(path)
+	}
+
+	/// Find the (1-indexed) number of a path, by reading through the table.
+	pub async fn find_path(
+		&self,
+		source: &mut SourceState<'_>,
+		path: &Path,
+	) -> Result<Option<u32>, TomoError> {
+		for n in 1..=self.count {
+			if &self.path(source, n).await? == path {
+				return Ok(Some(n));
+			}
+		}
+
+		Ok(None)
+	}
+
+	pub async fn attributes(
+		&self,
+		source: &mut SourceState<'_>,
+		n: u32,
+	) -> Result<Attributes, TomoError> {
+		let bytes = self.item(source, n).await?;
+		Ok(Attributes::from_item(&bytes)?)
+	}
+}
+
+/// The tree of a ReversePaths entry.
+#[derive(Clone, Debug)]
+pub(crate) struct ReverseTable {
+	data: EntryData,
+}
+
+impl ReverseTable {
+	pub async fn read(
+		source: &mut SourceState<'_>,
+		container: usize,
+		indic: &Indic,
+	) -> Result<Self, TomoError> {
+		Ok(Self {
+			data: EntryData::read(source, container, indic).await?,
+		})
+	}
+
+	/// Find the (0-indexed) positions of the indics which have a path, by walking down the tree.
+	///
+	/// Returns `None` if the path isn't in the tree.
+	pub async fn find(
+		&self,
+		source: &mut SourceState<'_>,
+		path: &Path,
+	) -> Result<Option<Vec<u64>>, TomoError> {
+		Ok(match self.node(source, path).await? {
+			Some(node) => Some(self.positions(source, node).await?),
+			None => None,
+		})
+	}
+
+	/// Find every path in the tree that matches a glob, with the positions of the indics which
+	/// have them. With `ancestors`, also find the paths that matches could be under.
+	///
+	/// Only walks down the branches where the glob could match. Each node has a single parent in
+	/// a well-formed tree, so reaching one twice is a parse error, rather than a walk that could
+	/// take exponentially long.
+	pub async fn matching(
+		&self,
+		source: &mut SourceState<'_>,
+		glob: &Glob,
+		ancestors: bool,
+	) -> Result<Vec<(Path, Vec<u64>)>, TomoError> {
+		let mut found = Vec::new();
+		let mut visited = HashSet::new();
+		let mut stack = vec![(Path::new(Vec::new()), 0)];
+		while let Some((path, node)) = stack.pop() {
+			if !visited.insert(node) {
+				return Err(TomoError::Parse(DekuError::Parse(format!(
+					"ReversePaths node at {} has more than one parent",
+					node
+				))));
+			}
+
+			if !glob.may_match_under(&path) {
+				continue;
+			}
+
+			if ancestors || glob.matches(&path) {
+				let positions = self.positions(source, node).await?;
+				if !positions.is_empty() {
+					found.push((path.clone(), positions));
+				}
+			}
+
+			let table = self.children_table(source, node).await?;
+			let count = self.data.u32(source, table).await? as u64;
+			for i in 0..count {
+				let (seg, child) = self.child_at(source, table, i).await?;
+				stack.push((path.join(seg), child));
+			}
+		}
+
+		found.sort_by(|(a, _), (b, _)| a.cmp(b));
+		Ok(found)
+	}
+
+	/// Walk down the tree to the node of a path, returning its offset.
+	async fn node(
+		&self,
+		source: &mut SourceState<'_>,
+		path: &Path,
+	) -> Result<Option<u64>, TomoError> {
+		let mut node = 0;
+		for seg in path.segments() {
+			node = match self.child(source, node, seg).await? {
+				Some(child) => child,
+				None => return Ok(None),
+			};
+		}
+		Ok(Some(node))
+	}
+
+	async fn positions(
+		&self,
+		source: &mut SourceState<'_>,
+		node: u64,
+	) -> Result<Vec<u64>, TomoError> {
+		let count = self.data.u32(source, node).await? as u64;
+		// the count is untrusted, and each position takes 8 bytes
+		let mut positions = Vec::with_capacity(count.min(self.data.len / 8) as usize);
+		for i in 0..count {
+			positions.push(self.data.u64(source, node + 4 + i * 8).await?);
+		}
+		Ok(positions)
+	}
+
+	async fn children_table(
+		&self,
+		source: &mut SourceState<'_>,
+		node: u64,
+	) -> Result<u64, TomoError> {
+		let positions = self.data.u32(source, node).await? as u64;
+		Ok(node + 4 + positions * 8)
+	}
+
+	/// Read the segment of the nth child in a children table, and the offset of its node.
+	///
+	/// Children always come after their parent's table, so walks down the tree end; anything else
+	/// is a parse error.
+	async fn child_at(
+		&self,
+		source: &mut SourceState<'_>,
+		table: u64,
+		n: u64,
+	) -> Result<(PathSeg, u64), TomoError> {
+		let offset = self.data.u64(source, table + 4 + n * 8).await?;
+		if offset <= table {
+			return Err(TomoError::Parse(DekuError::Parse(format!(
+				"ReversePaths child at {} points back to {}",
+				table, offset
+			))));
+		}
+		let len = self.data.u32(source, offset).await? as u64;
+		let bytes = self.data.bytes(source, offset + 4, len).await?;
+		Ok((PathSeg::from_item(&bytes)?, offset + 4 + len))
+	}
+
+	/// Binary search the children of a node for a segment, returning the offset of its node.
+	async fn child(
+		&self,
+		source: &mut SourceState<'_>,
+		node: u64,
+		seg: &PathSeg,
+	) -> Result<Option<u64>, TomoError> {
+		let table = self.children_table(source, node).await?;
+		let children = self.data.u32(source, table).await? as u64;
+
+		let (mut low, mut high) = (0, children);
+		while low < high {
+			let middle = low + (high - low) / 2;
+			let (found, child) = self.child_at(source, table, middle).await?;
+			match found.cmp(seg) {
+				Ordering::Less => low = middle + 1,
+				Ordering::Greater => high = middle,
+				Ordering::Equal => return Ok(Some(child)),
+			}
+		}
+
+		Ok(None)
+	}
+}
+
+/// The records of a Checksums entry, sorted by indic position.
+#[derive(Clone, Debug)]
+pub(crate) struct ChecksumTable {
+	count: u64,
+	data: EntryData,
+}
+
+impl ChecksumTable {
+	/// Scan the index for the first Checksums indic and read its table.
+	///
+	/// As with [`MetaTables::read`], the scan stops at the first indic with a path. Fails if the
+	/// entry is too short for the count of records it claims.
+	pub async fn find_in(
+		source: &mut SourceState<'_>,
+		container: usize,
+	) -> Result<Option<Self>, TomoError> {
+		let mut n = 0;
+		while let Some(indic) = source.indic(container, n).await? {
+			if indic.path != 0 {
+				break;
+			}
+			if indic.kind == IndicKind::Checksums {
+				let data = EntryData::read(source, container, &indic).await?;
+				let count = data.u32(source, 0).await? as u64;
+				let expected = 4 + count * CHECKSUM_SIZE as u64;
+				if expected > data.len {
+					return Err(TomoError::UnexpectedEof {
+						expected,
+						obtained: data.len,
+					});
+				}
+				return Ok(Some(Self { count, data }));
+			}
+			n += 1;
+		}
+
+		Ok(None)
+	}
+
+	async fn record(&self, source: &mut SourceState<'_>, n: u64) -> Result<Checksum, TomoError> {
+		let bytes = self
+			.data
+			.bytes(source, 4 + n * CHECKSUM_SIZE as u64, CHECKSUM_SIZE as u64)
+			.await?;
+		let (_, checksum) = Checksum::from_bytes((&bytes, 0))?;
+		Ok(checksum)
+	}
+
+	/// The checksums of an indic, by its (0-indexed) position, found by binary search.
+	pub async fn find(
+		&self,
+		source: &mut SourceState<'_>,
+		indic: u64,
+	) -> Result<Vec<Checksum>, TomoError> {
+		let (mut low, mut high) = (0, self.count);
+		while low < high {
+			let middle = low + (high - low) / 2;
+			if self.record(source, middle).await?.indic < indic {
+				low = middle + 1;
+			} else {
+				high = middle;
+			}
+		}
+
+		let mut found = Vec::new();
+		for n in low..self.count {
+			let record = self.record(source, n).await?;
+			if record.indic != indic {
+				break;
+			}
+			found.push(record);
+		}
+		Ok(found)
+	}
+
+	/// Every record, in order.
+	pub async fn all(&self, source: &mut SourceState<'_>) -> Result<Vec<Checksum>, TomoError> {
+		let mut records = Vec::new();
+		for n in 0..self.count {
+			records.push(self.record(source, n).await?);
+		}
+		Ok(records)
+	}
+}
+
+/// The Attributes and ReversePaths tables of a container, and its Paths indic.
+///
+/// The Paths table is left to [`SourceState::paths_table`], as lookups go through the ReversePaths
+/// table instead where there is one, and reading it can mean decompressing the whole entry.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MetaTables {
+	pub paths: Option<Indic>,
+	pub attrs: Option<LookupTable>,
+	pub reverse: Option<ReverseTable>,
+}
+
+impl MetaTables {
+	/// Scan the index for the first Paths, Attributes (of either version), and ReversePaths
+	/// indics and read the tables of the latter two.
+	///
+	/// These come before any indic with a path, so the scan stops there.
+	pub async fn read(source: &mut SourceState<'_>, container: usize) -> Result<Self, TomoError> {
+		let mut paths = None;
+		let mut attrs = None;
+		let mut reverse = None;
+
+		let mut n = 0;
+		while paths.is_none() || attrs.is_none() || reverse.is_none() {
+			let indic = match source.indic(container, n).await? {
+				Some(indic) if indic.path == 0 => indic,
+				_ => break,
+			};
+			n += 1;
+
+			match indic.kind {
+				IndicKind::Paths if paths.is_none() => paths = Some(indic),
+				IndicKind::Attributes | IndicKind::AttributesV2 if attrs.is_none() => {
+					attrs = Some(indic)
+				}
+				IndicKind::ReversePaths if reverse.is_none() => reverse = Some(indic),
+				_ => {}
+			}
+		}
+
+		let mut tables = Self {
+			paths,
+			..Self::default()
+		};
+		if let Some(indic) = attrs {
+			tables.attrs = Some(LookupTable::read(source, container, &indic).await?);
+		}
+		if let Some(indic) = reverse {
+			tables.reverse = Some(ReverseTable::read(source, container, &indic).await?);
+		}
+		Ok(tables)
+	}
+}
@@ -1,5 +1,16 @@
+// the DekuContainerRead impls that deku derives for every top-level type trip this lint; they're
+// separate items, so it can't be allowed on the types themselves
+#![allow(clippy::manual_div_ceil)]
+
 use deku::{ctx::Endian, prelude::*};
-use std::mem::size_of;
+use std::{
+	collections::BTreeMap,
+	ffi::OsString,
+	fmt,
+	mem::size_of,
+	path::{Component, PathBuf, Prefix, MAIN_SEPARATOR_STR},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 // *_SIZE constants measure the packed (deku) size, not the layout in memory (rust) size.
 
@@ -8,6 +19,13 @@ pub const MAGIC: [u8; 7] = *b"\0T\0M\0v\x01";
 #[derive(Clone, Debug, Default, DekuRead, DekuWrite)]
 #[deku(magic = b"\0T\0M\0v\x01", endian = "little")]
 pub struct ContainerHeader {
+	/// Whether the container has an Integrity entry, which is then its first indic.
+	#[deku(
+		bits = 1,
+		map = "|flag: u8| -> Result<_, DekuError> { Ok(flag == 1) }",
+		writer = "u8::from(*integrity).write(output, deku::ctx::BitSize(1))"
+	)]
+	pub integrity: bool,
 	pub mode: Mode,
 	pub index_bytes: u64,
 	pub entries_bytes: u64,
@@ -26,6 +44,12 @@ static_assertions::const_assert_eq!(CONTAINER_HEADER_SIZE, 24);
 // contains the path of the archived object. if the value is zero, the indic refers to the tomo
 // archive itself, usually used for the special types (0xF0 and above).
 // - the attributes index value is as with the path, but in the special Attributes data entry.
+// - an attributes item is the u16 mode, then any amount of tagged fields up to the end of the
+// item, each a u8 tag followed by the field data. readers must fail on tags they don't know.
+// - the Attributes entry is versioned by its indic kind: Attributes (0x10) items only have the
+// mode, while AttributesV2 (0x11) items may have tagged fields. writers use 0x10 whenever they
+// can, so that readers which predate tagged fields fail on the unknown 0x11 kind rather than
+// silently dropping fields. both kinds count towards the one-Attributes-entry limit.
 // - each data entry is preceded by a header describing the compression of the data.
 // - each data entry is individually compressed/encoded.
 // - there can be several layers of encoding.
@@ -44,28 +68,89 @@ static_assertions::const_assert_eq!(CONTAINER_HEADER_SIZE, 24);
 // soon as you've found them in the index, while continuing the index read, for example.
 // - archive mode is about archive concatenating: default is Stacked: given a path that exists in
 // two archives, the latter archive it appears in "wins."
+// - Overlay mode is Stacked plus deletions, like docker layers: a Whiteout (0x03) indic hides its
+// path and everything under it from earlier containers, and an Opaque (0x04) indic hides
+// everything under its path (but not the path itself) from earlier containers. these markers have
+// no entry data, and are ignored in containers of other modes. an opaque directory is usually
+// written as a Dir indic and an Opaque indic with the same path.
+// - Primacy mode is the opposite of Stacked: the first container to define a path wins. paths
+// from a Primacy container can't be replaced nor hidden by later containers, and a Primacy
+// container can only add paths that earlier containers don't have.
+// - Newest mode goes by modified date: when either the current winner for a path or the
+// candidate is from a Newest container, the candidate only wins if its mtime is at least as
+// recent. a missing mtime is older than any.
+// - modes only apply between containers: within a single container, the last indic for a path
+// always wins.
+// - when containers of different modes define the same path, Primacy takes precedence over
+// Newest, which takes precedence over Stacked and Overlay, whichever of the current winner or the
+// candidate the mode is on. readers can also override the mode of every container.
 // - archives can also be given sequentially to tomo for their mode to apply, there's no
 // requirement that they be catted.
 // - paths are stored in a platform-independent format, broken in their components ("segments").
-// it's possible to have absolute paths, URLs, drive-rooted paths, etc. Path segments are arbitrary
-// byte vecs, which may include null bytes, so tomo can be used to archive e.g. arbitrary KV data,
-// not just files.
+// it's possible to have absolute paths, URLs, drive-rooted paths, etc: besides plain Segments
+// (0x01), a path can start with a Root (0x10), a Windows Drive (0x11, the uppercase letter as a
+// byte), a Windows Unc (0x12, server then share), or a Url (0x13, scheme then authority). an
+// absolute path on a drive, share, or URL has a Root right after. names in all of these are
+// NUL-terminated, so they can't contain NUL bytes themselves. Path segments are otherwise
+// arbitrary byte vecs, so tomo can be used to archive e.g. arbitrary KV data, not just files.
 // - there's a limit of 16 million paths and 16 million attributes per tomo container, but you can
 // exceed that limit in a single file by catting.
-// - zstd dictionary mode is natively supported and the default on cli.
+// - a Symlink (0x05) entry's data is the link target, as raw bytes. a Hardlink (0x06) entry's data
+// is a HardlinkTarget: the (0-indexed) position in the same container's index of the indic it
+// links to, which must not itself be a Hardlink. CharDevice (0x07) and BlockDevice (0x08) entries'
+// data is a Device. Fifo (0x09) and Socket (0x0A) indics have no data. writers store all of these
+// without compression, as they're tiny.
+// - zstd dictionary mode is natively supported and the default on cli. the ZstdParams of an
+// entry refer to the indic holding the dictionary by its (0-indexed) position in the index. that
+// is usually a Dictionary (0x20) indic, but can be any. dictionary entries can be compressed, but
+// cannot themselves use a dictionary.
 // - hard limit of one Paths entry and one Attributes entry per container. for robustness sake, if
 // more than one such entry are in a container, only the first one is used.
-// - (todo) a ReversePaths entry type that contains a serialized tree of paths in filesystem layout
-// to indic numbers, to facilitate lookups by path. opt-out.
-
+// - a ReversePaths (0xF3) entry is a tree of paths in filesystem layout, mapping each path to the
+// (0-indexed) positions of the indics that have it, so a path can be found by reading only the
+// nodes along it. a node is a u32 count of positions, the u64 positions, a u32 count of children,
+// and a u64 offset for each child, from the start of the entry data. each child is a u32 length,
+// that many bytes of encoded PathSeg, and the child's node. children are sorted by segment: by
+// kind id, then bytewise by their fields in order. the root node, for the empty path, is at offset
+// zero. writers emit it unless asked not to, and readers use it when present. at most one per
+// container, as with Paths.
+// - a Checksums (0xF1) entry is a u32 count of records, then the records, sorted by indic then by
+// algorithm. a record is the u64 (0-indexed) position of an indic, a u8 algorithm (0x01 BLAKE3,
+// 0x02 SHA-256), and the 32-byte digest of that indic's entry data once decoded. an indic may have
+// a record for each algorithm, and indics without entry data have none. the Checksums entry can't
+// cover itself, but covers every other entry, metadata included. its data usually comes last, as
+// it's only known once everything else is written. at most one per container, as with Paths.
+// - an Integrity (0xF4) entry is a u8 algorithm and a 32-byte digest, as in Checksums records, of
+// the container header, then the whole index, then the entries of every other indic before the
+// first indic with a path (the "metadata" entries), in index order, as stored: encoded, and with
+// their entry headers. the Integrity entry itself is stored raw. its indic is always the first in
+// the index, and the high bit of the header's mode byte is set when there is one. readers check it
+// when loading the container if either says there is one, before trusting any offset in the index,
+// so a truncated or damaged index or metadata entry is caught early, and one damaged bit can't
+// hide the check. a set flag without an Integrity indic first is itself damage. containers without
+// one can't be checked this way. readers that predate the flag fail on the unknown mode.
+// - writers put the Paths, Attributes, ReversePaths, Checksums, and Integrity indics before any
+// indic with a path, so readers may stop looking for them there.
+
+/// The catting mode of a container. Stored in the low 7 bits of the mode byte of the header.
 #[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
-#[deku(type = "u8", ctx = "_: Endian")]
+#[deku(type = "u8", bits = "7", ctx = "_: Endian")]
 #[repr(u8)]
 pub enum Mode {
 	#[deku(id = "0x01")]
 	Stacked = 1,
+
+	#[deku(id = "0x02")]
+	Overlay = 2,
+
+	#[deku(id = "0x03")]
+	Primacy = 3,
+
+	#[deku(id = "0x04")]
+	Newest = 4,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for Mode {
 	fn default() -> Self {
 		Self::Stacked
@@ -79,9 +164,30 @@ pub enum IndicKind {
 	File,
 	#[deku(id = "0x02")]
 	Dir,
+	#[deku(id = "0x03")]
+	Whiteout,
+	#[deku(id = "0x04")]
+	Opaque,
+	#[deku(id = "0x05")]
+	Symlink,
+	#[deku(id = "0x06")]
+	Hardlink,
+	#[deku(id = "0x07")]
+	CharDevice,
+	#[deku(id = "0x08")]
+	BlockDevice,
+	#[deku(id = "0x09")]
+	Fifo,
+	#[deku(id = "0x0A")]
+	Socket,
 
 	#[deku(id = "0x10")]
 	Attributes,
+	#[deku(id = "0x11")]
+	AttributesV2,
+
+	#[deku(id = "0x20")]
+	Dictionary,
 
 	#[deku(id = "0xF0")]
 	Paths,
@@ -89,6 +195,10 @@ pub enum IndicKind {
 	Checksums,
 	#[deku(id = "0xF2")]
 	Signatures,
+	#[deku(id = "0xF3")]
+	ReversePaths,
+	#[deku(id = "0xF4")]
+	Integrity,
 }
 
 #[derive(Clone, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
@@ -99,6 +209,88 @@ pub enum PathSeg {
 
 	#[deku(id = "0x10")]
 	Root,
+
+	/// A Windows drive letter, uppercase. Followed by a [`PathSeg::Root`] if drive-absolute.
+	#[deku(id = "0x11")]
+	Drive(u8),
+
+	/// A Windows UNC server and share, each with a trailing NUL.
+	#[deku(id = "0x12")]
+	Unc {
+		#[deku(until = "|v| *v == 0")]
+		server: Vec<u8>,
+		#[deku(until = "|v| *v == 0")]
+		share: Vec<u8>,
+	},
+
+	/// A URL scheme and authority, each with a trailing NUL.
+	#[deku(id = "0x13")]
+	Url {
+		#[deku(until = "|v| *v == 0")]
+		scheme: Vec<u8>,
+		#[deku(until = "|v| *v == 0")]
+		authority: Vec<u8>,
+	},
+}
+
+impl PathSeg {
+	/// A segment from a name, adding the trailing NUL.
+	///
+	/// Names can't contain NUL bytes: writing a path with such a segment fails with
+	/// [`TomoError::InvalidPath`](crate::TomoError::InvalidPath).
+	pub fn from_name(name: impl AsRef<[u8]>) -> Self {
+		Self::Segment(nul_terminated(name.as_ref()))
+	}
+
+	/// A UNC server and share, adding the trailing NULs.
+	pub fn unc(server: impl AsRef<[u8]>, share: impl AsRef<[u8]>) -> Self {
+		Self::Unc {
+			server: nul_terminated(server.as_ref()),
+			share: nul_terminated(share.as_ref()),
+		}
+	}
+
+	/// A URL scheme and authority, adding the trailing NULs.
+	pub fn url(scheme: impl AsRef<[u8]>, authority: impl AsRef<[u8]>) -> Self {
+		Self::Url {
+			scheme: nul_terminated(scheme.as_ref()),
+			authority: nul_terminated(authority.as_ref()),
+		}
+	}
+
+	pub(crate) fn from_item(bytes: &[u8]) -> Result<Self, DekuError> {
+		let (_, seg) = Self::read(bytes.view_bits(), Endian::Little)?;
+		Ok(seg)
+	}
+
+	pub(crate) fn to_item(&self) -> Result<Vec<u8>, DekuError> {
+		let mut bits = BitVec::<Msb0, u8>::new();
+		self.write(&mut bits, Endian::Little)?;
+		Ok(bits.into_vec())
+	}
+
+	/// Whether this segment can be written: each of its names ends with a NUL, and has no other.
+	pub fn is_valid(&self) -> bool {
+		let name = |bytes: &[u8]| {
+			bytes
+				.split_last()
+				.is_some_and(|(last, name)| *last == 0 && !name.contains(&0))
+		};
+		match self {
+			Self::Segment(bytes) => name(bytes),
+			Self::Unc { server, share } => name(server) && name(share),
+			Self::Url { scheme, authority } => name(scheme) && name(authority),
+			Self::Root | Self::Drive(_) => true,
+		}
+	}
+
+	/// The name of a segment, without its trailing NUL, or `None` for other kinds of segments.
+	pub fn name(&self) -> Option<&[u8]> {
+		match self {
+			Self::Segment(bytes) => Some(without_nul(bytes)),
+			_ => None,
+		}
+	}
 }
 
 #[derive(Clone, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
@@ -110,6 +302,309 @@ pub struct Path {
 	segments: Vec<PathSeg>,
 }
 
+impl Path {
+	pub fn new(segments: Vec<PathSeg>) -> Self {
+		Self {
+			segcount: segments.len() as u32,
+			segments,
+		}
+	}
+
+	pub fn segments(&self) -> &[PathSeg] {
+		&self.segments
+	}
+
+	/// The path without its last segment, or `None` if it has no segments.
+	pub fn parent(&self) -> Option<Path> {
+		let (_, parent) = self.segments.split_last()?;
+		Some(Self::new(parent.to_vec()))
+	}
+
+	/// Whether `base` is this path or one of its ancestors.
+	pub fn starts_with(&self, base: &Path) -> bool {
+		self.segments.starts_with(&base.segments)
+	}
+
+	/// This path with another segment at the end.
+	pub fn join(&self, seg: PathSeg) -> Path {
+		let mut segments = self.segments.clone();
+		segments.push(seg);
+		Self::new(segments)
+	}
+
+	/// A relative path from segment names, without their trailing NULs.
+	///
+	/// As with [`PathSeg::from_name`], names can't contain NUL bytes.
+	pub fn from_names<S: AsRef<[u8]>>(names: &[S]) -> Self {
+		Self::new(names.iter().map(PathSeg::from_name).collect())
+	}
+
+	/// Iterate over the segments.
+	pub fn iter(&self) -> std::slice::Iter<'_, PathSeg> {
+		self.segments.iter()
+	}
+
+	/// Convert to a filesystem path.
+	///
+	/// On Unix, segment names are used as-is, so this is the exact reverse of converting from a
+	/// [`std::path::Path`]. Elsewhere, names that aren't valid UTF-8 are converted lossily, with
+	/// invalid sequences replaced by U+FFFD. On all platforms, separators within names are not
+	/// escaped, so they split the name into several components.
+	///
+	/// Drives and UNC shares are written in Windows form (`C:`, `\\server\share`), and URL
+	/// prefixes as `scheme://authority`, which only make sense as paths on some platforms.
+	pub fn to_path_buf(&self) -> PathBuf {
+		let mut buf = OsString::new();
+		let mut separate = false;
+		for seg in &self.segments {
+			match seg {
+				PathSeg::Segment(_) => {
+					if separate {
+						buf.push(MAIN_SEPARATOR_STR);
+					}
+					buf.push(os_name(seg.name().unwrap_or_default()));
+					separate = true;
+				}
+				PathSeg::Root => {
+					buf.push(MAIN_SEPARATOR_STR);
+					separate = false;
+				}
+				PathSeg::Drive(letter) => {
+					buf.push(format!("{}:", *letter as char));
+					separate = false;
+				}
+				PathSeg::Unc { server, share } => {
+					buf.push(r"\\");
+					buf.push(os_name(without_nul(server)));
+					buf.push(r"\");
+					buf.push(os_name(without_nul(share)));
+					separate = true;
+				}
+				PathSeg::Url { scheme, authority } => {
+					buf.push(os_name(without_nul(scheme)));
+					buf.push("://");
+					buf.push(os_name(without_nul(authority)));
+					separate = true;
+				}
+			}
+		}
+		PathBuf::from(buf)
+	}
+
+	/// Parse a Windows path, on any platform.
+	///
+	/// Both `\` and `/` are separators. A leading drive letter becomes a [`PathSeg::Drive`], a
+	/// leading `\\server\share` a [`PathSeg::Unc`] (always followed by a [`PathSeg::Root`]),
+	/// and the verbatim forms of these (`\\?\C:\`, `\\?\UNC\server\share`) are recognised
+	/// too. As with `/`-separated paths, empty and `.` parts are dropped.
+	///
+	/// ```
+	/// use tomo::parsers::{Path, PathSeg};
+	/// let path = Path::from_windows(r"c:\Users\tomo");
+	/// assert_eq!(path, Path::new(vec![
+	///     PathSeg::Drive(b'C'),
+	///     PathSeg::Root,
+	///     PathSeg::from_name("Users"),
+	///     PathSeg::from_name("tomo"),
+	/// ]));
+	/// assert_eq!(path.to_string(), "C:/Users/tomo");
+	/// ```
+	pub fn from_windows(path: &str) -> Self {
+		let is_separator = |c: char| c == '\\' || c == '/';
+		let mut segments = Vec::new();
+
+		let mut rest = path;
+		let unc = if let Some(verbatim) = path.strip_prefix(r"\\?\") {
+			rest = verbatim;
+			verbatim.strip_prefix(r"UNC\")
+		} else {
+			let mut chars = path.chars();
+			match (chars.next(), chars.next()) {
+				(Some(a), Some(b)) if is_separator(a) && is_separator(b) => Some(chars.as_str()),
+				_ => None,
+			}
+		};
+
+		if let Some(unc) = unc {
+			let mut parts = unc.splitn(3, is_separator);
+			let server = parts.next().unwrap_or_default();
+			let share = parts.next().unwrap_or_default();
+			segments.push(PathSeg::unc(server, share));
+			segments.push(PathSeg::Root);
+			rest = parts.next().unwrap_or_default();
+		} else {
+			let bytes = rest.as_bytes();
+			if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+				segments.push(PathSeg::Drive(bytes[0].to_ascii_uppercase()));
+				rest = &rest[2..];
+			}
+			if rest.starts_with(is_separator) {
+				segments.push(PathSeg::Root);
+			}
+		}
+
+		segments.extend(
+			rest.split(is_separator)
+				.filter(|part| !part.is_empty() && *part != ".")
+				.map(PathSeg::from_name),
+		);
+		Self::new(segments)
+	}
+
+	/// Parse a URL of the form `scheme://authority/path`, or `None` if it's not of that form.
+	///
+	/// The scheme and authority become a [`PathSeg::Url`], followed by a [`PathSeg::Root`] if
+	/// there's a path, and the path is split on `/` like any other. Nothing is percent-decoded,
+	/// and queries and fragments are not treated specially.
+	pub fn from_url(url: &str) -> Option<Self> {
+		let (scheme, rest) = url.split_once("://")?;
+		let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+			&& scheme
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+		if !valid_scheme {
+			return None;
+		}
+
+		let (authority, path) = match rest.find('/') {
+			Some(slash) => rest.split_at(slash),
+			None => (rest, ""),
+		};
+
+		let mut segments = vec![PathSeg::url(scheme, authority)];
+		segments.extend(Path::from(path).segments);
+		Some(Self::new(segments))
+	}
+}
+
+impl<'p> IntoIterator for &'p Path {
+	type Item = &'p PathSeg;
+	type IntoIter = std::slice::Iter<'p, PathSeg>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+/// Converts component by component: the root becomes [`PathSeg::Root`], `.` components are
+/// dropped, and everything else is a [`PathSeg::Segment`], including `..`.
+///
+/// Windows prefixes become a [`PathSeg::Drive`] or a [`PathSeg::Unc`], and other kinds of prefixes
+/// (like device namespaces) are kept as a segment.
+///
+/// On Unix, names are taken as bytes, so this is lossless. Elsewhere, names that aren't valid
+/// Unicode are converted lossily, with invalid sequences replaced by U+FFFD.
+impl From<&std::path::Path> for Path {
+	fn from(path: &std::path::Path) -> Self {
+		Self::new(
+			path.components()
+				.filter_map(|component| match component {
+					Component::RootDir => Some(PathSeg::Root),
+					Component::CurDir => None,
+					Component::ParentDir => Some(PathSeg::from_name("..")),
+					Component::Normal(name) => Some(PathSeg::from_name(name_bytes(name))),
+					Component::Prefix(prefix) => Some(match prefix.kind() {
+						Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+							PathSeg::Drive(letter.to_ascii_uppercase())
+						}
+						Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+							PathSeg::unc(name_bytes(server), name_bytes(share))
+						}
+						_ => PathSeg::from_name(name_bytes(prefix.as_os_str())),
+					}),
+				})
+				.collect(),
+		)
+	}
+}
+
+/// Parses a `/`-separated path. A leading `/` becomes [`PathSeg::Root`], and empty and `.` parts
+/// are dropped.
+impl From<&str> for Path {
+	fn from(path: &str) -> Self {
+		let root = path.starts_with('/').then_some(PathSeg::Root);
+		Self::new(
+			root.into_iter()
+				.chain(
+					path.split('/')
+						.filter(|part| !part.is_empty() && *part != ".")
+						.map(PathSeg::from_name),
+				)
+				.collect(),
+		)
+	}
+}
+
+/// Segments separated by `/`, with a leading `/` for [`PathSeg::Root`], drives as `C:`, UNC
+/// shares as `//server/share`, and URL prefixes as `scheme://authority`. Names that aren't valid
+/// UTF-8 are shown lossily.
+impl fmt::Display for Path {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let lossy = |bytes: &[u8]| String::from_utf8_lossy(without_nul(bytes)).into_owned();
+		let mut separate = false;
+		for seg in &self.segments {
+			match seg {
+				PathSeg::Segment(name) => {
+					if separate {
+						f.write_str("/")?;
+					}
+					f.write_str(&lossy(name))?;
+					separate = true;
+				}
+				PathSeg::Root => {
+					f.write_str("/")?;
+					separate = false;
+				}
+				PathSeg::Drive(letter) => {
+					write!(f, "{}:", *letter as char)?;
+					separate = false;
+				}
+				PathSeg::Unc { server, share } => {
+					write!(f, "//{}/{}", lossy(server), lossy(share))?;
+					separate = true;
+				}
+				PathSeg::Url { scheme, authority } => {
+					write!(f, "{}://{}", lossy(scheme), lossy(authority))?;
+					separate = true;
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+fn nul_terminated(bytes: &[u8]) -> Vec<u8> {
+	let mut bytes = bytes.to_vec();
+	bytes.push(0);
+	bytes
+}
+
+fn without_nul(bytes: &[u8]) -> &[u8] {
+	bytes.strip_suffix(&[0]).unwrap_or(bytes)
+}
+
+#[cfg(unix)]
+fn name_bytes(name: &std::ffi::OsStr) -> Vec<u8> {
+	use std::os::unix::ffi::OsStrExt;
+	name.as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn name_bytes(name: &std::ffi::OsStr) -> Vec<u8> {
+	name.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+fn os_name(name: &[u8]) -> OsString {
+	use std::os::unix::ffi::OsStringExt;
+	OsString::from_vec(name.to_vec())
+}
+
+#[cfg(not(unix))]
+fn os_name(name: &[u8]) -> OsString {
+	OsString::from(String::from_utf8_lossy(name).into_owned())
+}
+
 #[derive(Clone, Debug, DekuRead, Eq, PartialEq, Ord, PartialOrd)]
 #[deku(endian = "little")]
 pub struct PathsEntryHeader {
@@ -126,10 +621,334 @@ pub struct Lookup {
 pub const LOOKUP_SIZE: usize = size_of::<u32>() + size_of::<u64>();
 static_assertions::const_assert_eq!(LOOKUP_SIZE, 12);
 
-#[derive(Clone, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
-#[deku(ctx = "_: Endian")]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Attributes {
-	mode: u16,
+	/// Permission bits, including [`Attributes::SETUID`], [`Attributes::SETGID`], and
+	/// [`Attributes::STICKY`].
+	pub mode: u16,
+
+	/// Last modification time.
+	pub mtime: Option<Timestamp>,
+	/// Last access time.
+	pub atime: Option<Timestamp>,
+	/// Last status change time.
+	pub ctime: Option<Timestamp>,
+
+	/// Numeric owner user id.
+	pub uid: Option<u32>,
+	/// Numeric owner group id.
+	pub gid: Option<u32>,
+	/// Owner user name.
+	pub user: Option<Vec<u8>>,
+	/// Owner group name.
+	pub group: Option<Vec<u8>>,
+
+	/// Extended attributes, by name.
+	///
+	/// POSIX ACLs are stored as xattrs on Linux, but here they're in [`Attributes::access_acl`]
+	/// and [`Attributes::default_acl`] instead.
+	pub xattrs: BTreeMap<Vec<u8>, Vec<u8>>,
+	/// POSIX access ACL.
+	pub access_acl: Option<Vec<AclEntry>>,
+	/// POSIX default ACL, for directories.
+	pub default_acl: Option<Vec<AclEntry>>,
+}
+
+impl Attributes {
+	pub const SETUID: u16 = 0o4000;
+	pub const SETGID: u16 = 0o2000;
+	pub const STICKY: u16 = 0o1000;
+
+	pub fn new(mode: u16) -> Self {
+		Self {
+			mode,
+			..Self::default()
+		}
+	}
+
+	/// Read attributes from filesystem metadata.
+	///
+	/// On unix this includes everything but the user and group names. Elsewhere, only the
+	/// read-only flag (as the mode) and the times that are available.
+	pub fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::MetadataExt;
+			let time = |secs: i64, nanos: i64| Timestamp {
+				secs,
+				nanos: nanos as u32,
+			};
+
+			Self {
+				mode: (metadata.mode() & 0o7777) as u16,
+				mtime: Some(time(metadata.mtime(), metadata.mtime_nsec())),
+				atime: Some(time(metadata.atime(), metadata.atime_nsec())),
+				ctime: Some(time(metadata.ctime(), metadata.ctime_nsec())),
+				uid: Some(metadata.uid()),
+				gid: Some(metadata.gid()),
+				..Self::default()
+			}
+		}
+
+		#[cfg(not(unix))]
+		{
+			Self {
+				mode: if metadata.permissions().readonly() {
+					0o444
+				} else {
+					0o644
+				},
+				mtime: metadata.modified().ok().map(Timestamp::from),
+				atime: metadata.accessed().ok().map(Timestamp::from),
+				..Self::default()
+			}
+		}
+	}
+
+	pub fn is_setuid(&self) -> bool {
+		self.mode & Self::SETUID != 0
+	}
+
+	pub fn is_setgid(&self) -> bool {
+		self.mode & Self::SETGID != 0
+	}
+
+	pub fn is_sticky(&self) -> bool {
+		self.mode & Self::STICKY != 0
+	}
+
+	/// Whether there's anything besides the mode, which needs an AttributesV2 entry.
+	pub fn has_fields(&self) -> bool {
+		self != &Self::new(self.mode)
+	}
+
+	/// Parse an item from the Attributes entry.
+	pub fn from_item(bytes: &[u8]) -> Result<Self, DekuError> {
+		let (mut rest, mode) = u16::read(bytes.view_bits(), Endian::Little)?;
+		let mut attrs = Self::new(mode);
+		while !rest.is_empty() {
+			let (r, field) = AttributeField::read(rest, Endian::Little)?;
+			rest = r;
+
+			match field {
+				AttributeField::Mtime(time) => attrs.mtime = Some(time),
+				AttributeField::Atime(time) => attrs.atime = Some(time),
+				AttributeField::Ctime(time) => attrs.ctime = Some(time),
+				AttributeField::Uid(id) => attrs.uid = Some(id),
+				AttributeField::Gid(id) => attrs.gid = Some(id),
+				AttributeField::User { name, .. } => attrs.user = Some(name),
+				AttributeField::Group { name, .. } => attrs.group = Some(name),
+				AttributeField::Xattr { name, value, .. } => {
+					attrs.xattrs.insert(name, value);
+				}
+				AttributeField::AccessAcl { entries, .. } => attrs.access_acl = Some(entries),
+				AttributeField::DefaultAcl { entries, .. } => attrs.default_acl = Some(entries),
+			}
+		}
+
+		Ok(attrs)
+	}
+
+	/// Serialise as an item of the Attributes entry.
+	pub fn to_item(&self) -> Result<Vec<u8>, DekuError> {
+		let mut fields = Vec::new();
+		if let Some(time) = self.mtime {
+			fields.push(AttributeField::Mtime(time));
+		}
+		if let Some(time) = self.atime {
+			fields.push(AttributeField::Atime(time));
+		}
+		if let Some(time) = self.ctime {
+			fields.push(AttributeField::Ctime(time));
+		}
+		if let Some(id) = self.uid {
+			fields.push(AttributeField::Uid(id));
+		}
+		if let Some(id) = self.gid {
+			fields.push(AttributeField::Gid(id));
+		}
+		if let Some(name) = &self.user {
+			fields.push(AttributeField::User {
+				len: name.len() as u16,
+				name: name.clone(),
+			});
+		}
+		if let Some(name) = &self.group {
+			fields.push(AttributeField::Group {
+				len: name.len() as u16,
+				name: name.clone(),
+			});
+		}
+		for (name, value) in &self.xattrs {
+			fields.push(AttributeField::Xattr {
+				name_len: name.len() as u16,
+				name: name.clone(),
+				value_len: value.len() as u32,
+				value: value.clone(),
+			});
+		}
+		if let Some(entries) = &self.access_acl {
+			fields.push(AttributeField::AccessAcl {
+				count: entries.len() as u16,
+				entries: entries.clone(),
+			});
+		}
+		if let Some(entries) = &self.default_acl {
+			fields.push(AttributeField::DefaultAcl {
+				count: entries.len() as u16,
+				entries: entries.clone(),
+			});
+		}
+
+		let mut bits = BitVec::<Msb0, u8>::new();
+		self.mode.write(&mut bits, Endian::Little)?;
+		for field in fields {
+			field.write(&mut bits, Endian::Little)?;
+		}
+		Ok(bits.into_vec())
+	}
+}
+
+/// A tagged field of an Attributes item, following the mode.
+#[derive(Clone, Debug, DekuRead, DekuWrite, Eq, PartialEq)]
+#[deku(type = "u8", ctx = "endian: Endian", endian = "endian")]
+enum AttributeField {
+	#[deku(id = "0x01")]
+	Mtime(Timestamp),
+	#[deku(id = "0x02")]
+	Atime(Timestamp),
+	#[deku(id = "0x03")]
+	Ctime(Timestamp),
+
+	#[deku(id = "0x10")]
+	Uid(u32),
+	#[deku(id = "0x11")]
+	Gid(u32),
+	#[deku(id = "0x12")]
+	User {
+		len: u16,
+		#[deku(count = "len")]
+		name: Vec<u8>,
+	},
+	#[deku(id = "0x13")]
+	Group {
+		len: u16,
+		#[deku(count = "len")]
+		name: Vec<u8>,
+	},
+
+	#[deku(id = "0x20")]
+	Xattr {
+		name_len: u16,
+		#[deku(count = "name_len")]
+		name: Vec<u8>,
+		value_len: u32,
+		#[deku(count = "value_len")]
+		value: Vec<u8>,
+	},
+	#[deku(id = "0x21")]
+	AccessAcl {
+		count: u16,
+		#[deku(count = "count")]
+		entries: Vec<AclEntry>,
+	},
+	#[deku(id = "0x22")]
+	DefaultAcl {
+		count: u16,
+		#[deku(count = "count")]
+		entries: Vec<AclEntry>,
+	},
+}
+
+/// An entry of a POSIX ACL.
+///
+/// This is laid out as in the Linux `system.posix_acl_*` xattrs.
+#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
+#[deku(ctx = "endian: Endian", endian = "endian")]
+pub struct AclEntry {
+	pub tag: AclTag,
+	/// Read (4), write (2), and execute (1) bits.
+	pub perm: u16,
+	/// The user or group id, for [`AclTag::User`] and [`AclTag::Group`] entries.
+	pub id: u32,
+}
+
+#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
+#[deku(type = "u16", ctx = "endian: Endian", endian = "endian")]
+pub enum AclTag {
+	#[deku(id = "0x01")]
+	UserObj,
+	#[deku(id = "0x02")]
+	User,
+	#[deku(id = "0x04")]
+	GroupObj,
+	#[deku(id = "0x08")]
+	Group,
+	#[deku(id = "0x10")]
+	Mask,
+	#[deku(id = "0x20")]
+	Other,
+}
+
+/// A point in time, as seconds and nanoseconds since the unix epoch.
+///
+/// The nanoseconds are always positive, so a second before the epoch is `secs: -1, nanos: 0`,
+/// and half a second before is `secs: -1, nanos: 500_000_000`. Nanoseconds of a second or more
+/// fail to parse.
+#[derive(Clone, Copy, Debug, Default, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
+#[deku(ctx = "endian: Endian", endian = "endian")]
+pub struct Timestamp {
+	pub secs: i64,
+	#[deku(map = "Timestamp::check_nanos")]
+	pub nanos: u32,
+}
+
+impl Timestamp {
+	fn check_nanos(nanos: u32) -> Result<u32, DekuError> {
+		if nanos < 1_000_000_000 {
+			Ok(nanos)
+		} else {
+			Err(DekuError::Parse(format!(
+				"timestamp nanoseconds out of range: {}",
+				nanos
+			)))
+		}
+	}
+
+	/// This time as a [`SystemTime`], or `None` if the platform can't represent it.
+	pub fn to_system_time(self) -> Option<SystemTime> {
+		let nanos = Duration::from_nanos(self.nanos.into());
+		if self.secs >= 0 {
+			UNIX_EPOCH.checked_add(Duration::from_secs(self.secs as u64))?
+		} else {
+			UNIX_EPOCH.checked_sub(Duration::from_secs(self.secs.unsigned_abs()))?
+		}
+		.checked_add(nanos)
+	}
+}
+
+impl From<SystemTime> for Timestamp {
+	fn from(time: SystemTime) -> Self {
+		match time.duration_since(UNIX_EPOCH) {
+			Ok(after) => Self {
+				secs: after.as_secs() as i64,
+				nanos: after.subsec_nanos(),
+			},
+			Err(err) => {
+				let before = err.duration();
+				match before.subsec_nanos() {
+					0 => Self {
+						secs: -(before.as_secs() as i64),
+						nanos: 0,
+					},
+					nanos => Self {
+						secs: -(before.as_secs() as i64) - 1,
+						nanos: 1_000_000_000 - nanos,
+					},
+				}
+			}
+		}
+	}
 }
 
 #[derive(Clone, Copy, Debug, DekuRead, DekuWrite)]
@@ -145,6 +964,19 @@ pub struct Indic {
 	pub length: u64,
 }
 
+impl Indic {
+	pub fn new(kind: IndicKind, path: u32, attrs: u32, offset: u64, length: u64) -> Self {
+		Self {
+			kind,
+			path,
+			attrs,
+			_reserved: 0,
+			offset,
+			length,
+		}
+	}
+}
+
 pub const INDIC_SIZE: u64 = (size_of::<IndicKind>() +
     3 + // "u24"
     3 + // "u24"
@@ -153,6 +985,58 @@ pub const INDIC_SIZE: u64 = (size_of::<IndicKind>() +
     size_of::<u64>()) as u64;
 static_assertions::const_assert_eq!(INDIC_SIZE, 24);
 
+/// The entry data of a Hardlink indic.
+#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
+#[deku(endian = "little")]
+pub struct HardlinkTarget {
+	/// Position of the linked indic in the container's index.
+	pub indic: u64,
+}
+
+/// The entry data of a CharDevice or BlockDevice indic.
+#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
+#[deku(endian = "little")]
+pub struct Device {
+	pub major: u32,
+	pub minor: u32,
+}
+
+/// A digest algorithm used in Checksums entries.
+#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[deku(type = "u8", ctx = "_: Endian")]
+pub enum ChecksumAlgorithm {
+	#[deku(id = "0x01")]
+	Blake3,
+	#[deku(id = "0x02")]
+	Sha256,
+}
+
+/// Both algorithms give 32-byte digests.
+pub const DIGEST_SIZE: usize = 32;
+
+/// A record of a Checksums entry: the digest of the decoded entry data of an indic.
+#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
+#[deku(endian = "little")]
+pub struct Checksum {
+	/// Position of the indic in the container's index.
+	pub indic: u64,
+	pub algorithm: ChecksumAlgorithm,
+	pub digest: [u8; DIGEST_SIZE],
+}
+pub const CHECKSUM_SIZE: usize = size_of::<u64>() + size_of::<u8>() + DIGEST_SIZE;
+static_assertions::const_assert_eq!(CHECKSUM_SIZE, 41);
+
+/// The entry data of an Integrity indic: the digest of the container's header, index, and
+/// metadata entries.
+#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
+#[deku(endian = "little")]
+pub struct Integrity {
+	pub algorithm: ChecksumAlgorithm,
+	pub digest: [u8; DIGEST_SIZE],
+}
+pub const INTEGRITY_SIZE: usize = size_of::<u8>() + DIGEST_SIZE;
+static_assertions::const_assert_eq!(INTEGRITY_SIZE, 33);
+
 #[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
 #[deku(type = "u8", ctx = "_: Endian")]
 pub enum Encoding {
@@ -167,6 +1051,7 @@ pub enum Encoding {
 	Tomo,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for Encoding {
 	fn default() -> Self {
 		Self::Raw
@@ -177,7 +1062,7 @@ impl Default for Encoding {
 #[deku(endian = "little")]
 pub struct ZstdParams {
 	/// Index of the indic that points to the zstd dictionary data file
-	dictionary: u64,
+	pub dictionary: u64,
 }
 
 #[derive(Clone, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
@@ -197,12 +1082,42 @@ pub struct EntryHeader {
 	#[deku(bits = 6)]
 	_reserved: u8,
 	encoding: Encoding,
-	#[deku(update = "self.params.len()", cond = "*has_params == 1", default = "0")]
+	#[deku(
+		update = "self.params.len()",
+		cond = "*has_params == 1",
+		default = "0",
+		writer = "if *has_params == 1 { params_bytes.write(output, Endian::Little) } else { Ok(()) }"
+	)]
 	params_bytes: u16,
 	#[deku(count = "params_bytes")]
 	params: Vec<u8>,
 }
 
+impl EntryHeader {
+	pub fn new(encoding: Encoding, params: Vec<u8>) -> Self {
+		Self {
+			has_params: if params.is_empty() { 0 } else { 1 },
+			nested: 0,
+			_reserved: 0,
+			encoding,
+			params_bytes: params.len() as u16,
+			params,
+		}
+	}
+
+	pub fn encoding(&self) -> Encoding {
+		self.encoding
+	}
+
+	pub fn params(&self) -> &[u8] {
+		&self.params
+	}
+
+	pub fn is_nested(&self) -> bool {
+		self.nested == 1
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -214,6 +1129,8 @@ mod tests {
 	#[derive(Clone, Debug, Default, DekuRead, DekuWrite)]
 	#[deku(magic = b"\0T\0M\0v\x01", endian = "little")]
 	struct TestContainer {
+		#[deku(bits = 1)]
+		integrity: u8,
 		mode: Mode,
 		#[deku(
 			update = "{ use crate::parsers::INDIC_SIZE; self.index.len() as u64 * INDIC_SIZE }"
@@ -243,6 +1160,7 @@ mod tests {
 
 	#[derive(Clone, Debug)]
 	struct TestEntry {
+		#[allow(dead_code)]
 		indic: TestIndic,
 		header: EntryHeader,
 		data: Vec<u8>,
@@ -267,7 +1185,7 @@ mod tests {
 			self.entries.len()
 		}
 
-		fn from_offset(&self, offset: u64) -> Option<&TestEntry> {
+		fn by_offset(&self, offset: u64) -> Option<&TestEntry> {
 			let index = *self.offsets.get(&offset)?;
 			self.entries.get(index)
 		}
@@ -341,6 +1259,7 @@ mod tests {
 
 	#[derive(Clone, Copy, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
 	#[deku(ctx = "_: Endian")]
+	#[allow(dead_code)]
 	struct TestLookup {
 		offset: u64,
 	}
@@ -356,7 +1275,14 @@ mod tests {
 		)]
 		_lookup: (), // parsed but discarded (only useful when doing partial parses)
 		#[deku(count = "attr_count")]
-		attrs: Vec<Attributes>,
+		attrs: Vec<TestAttributes>,
+	}
+
+	// only the mode, without any tagged fields
+	#[derive(Clone, Debug, DekuRead, DekuWrite, Eq, PartialEq, Ord, PartialOrd)]
+	#[deku(ctx = "_: Endian")]
+	struct TestAttributes {
+		mode: u16,
 	}
 
 	impl DekuWrite<Endian> for AttributesEntry {
@@ -366,7 +1292,7 @@ mod tests {
 	}
 
 	fn write_lookup<T: DekuWrite<Endian>>(
-		list: &Vec<T>,
+		list: &[T],
 		output: &mut BitVec<Msb0, u8>,
 		ctx: Endian,
 	) -> Result<(), DekuError> {
@@ -386,7 +1312,7 @@ mod tests {
 			item.write(output, ctx)?;
 
 			// unwrap: infaillible
-			lookup.write(&offset.to_le_bytes()).unwrap();
+			lookup.write_all(&offset.to_le_bytes()).unwrap();
 		}
 
 		let mut lookup: BitVec<Msb0, u8> = BitVec::try_from_vec(lookup.into_inner()).unwrap();
@@ -457,6 +1383,21 @@ mod tests {
 		assert_eq!(value.mode, Mode::Stacked);
 		assert_eq!(value.entries_bytes, 0);
 		assert_eq!(value.index_bytes, 0);
+		assert!(!value.integrity);
+	}
+
+	#[test]
+	fn integrity_flag() {
+		let mut data = Vec::new();
+		data.extend(&MAGIC);
+		data.push(0x80 | Mode::Overlay as u8);
+		data.extend(&0_u64.to_le_bytes());
+		data.extend(&0_u64.to_le_bytes());
+
+		let (_, value) = ContainerHeader::from_bytes((&data, 0)).unwrap();
+		assert!(value.integrity);
+		assert_eq!(value.mode, Mode::Overlay);
+		assert_eq!(value.to_bytes().unwrap(), data);
 	}
 
 	#[test]
@@ -476,13 +1417,284 @@ mod tests {
 		assert_eq!(value.entries.len(), 0);
 		assert_eq!(value.index.len(), 0);
 
-		let ((rest2, _), value) = TestContainer::from_bytes((&rest, 0)).unwrap();
+		let ((rest2, _), value) = TestContainer::from_bytes((rest, 0)).unwrap();
 		assert_eq!(rest2.len(), 0);
 		assert_eq!(value.mode, Mode::Stacked);
 		assert_eq!(value.entries.len(), 0);
 		assert_eq!(value.index.len(), 0);
 	}
 
+	#[test]
+	fn entry_header_params() {
+		let raw = EntryHeader::new(Encoding::Raw, Vec::new());
+		assert_eq!(raw.to_bytes().unwrap(), vec![0b00_000000, 0x00]);
+
+		let zstd = EntryHeader::new(Encoding::Zstd, vec![1, 2, 3]);
+		let data = zstd.to_bytes().unwrap();
+		assert_eq!(data, vec![0b10_000000, 0x01, 3, 0, 1, 2, 3]);
+
+		let ((rest, _), header) = EntryHeader::from_bytes((&data, 0)).unwrap();
+		assert_eq!(rest.len(), 0);
+		assert_eq!(header.encoding, Encoding::Zstd);
+		assert_eq!(header.params, vec![1, 2, 3]);
+	}
+
+	#[test]
+	fn attributes_item() {
+		let plain = Attributes::new(0o644);
+		assert_eq!(plain.to_item().unwrap(), vec![0xA4, 0x01]);
+		assert_eq!(Attributes::from_item(&[0xA4, 0x01]).unwrap(), plain);
+
+		let attrs = Attributes {
+			mtime: Some(Timestamp {
+				secs: 1_600_000_000,
+				nanos: 42,
+			}),
+			..Attributes::new(0o644)
+		};
+		let item = attrs.to_item().unwrap();
+		assert_eq!(item.len(), 2 + 1 + 8 + 4);
+		assert_eq!(item[2], 0x01);
+		assert_eq!(Attributes::from_item(&item).unwrap(), attrs);
+
+		let rich = Attributes {
+			mode: 0o4755,
+			mtime: Some(Timestamp { secs: 3, nanos: 1 }),
+			atime: Some(Timestamp { secs: 2, nanos: 1 }),
+			ctime: Some(Timestamp { secs: -1, nanos: 1 }),
+			uid: Some(1000),
+			gid: Some(100),
+			user: Some(b"alice".to_vec()),
+			group: Some(b"users".to_vec()),
+			xattrs: vec![(b"user.tag".to_vec(), b"blue".to_vec())]
+				.into_iter()
+				.collect(),
+			access_acl: Some(vec![AclEntry {
+				tag: AclTag::User,
+				perm: 7,
+				id: 1000,
+			}]),
+			default_acl: None,
+		};
+		assert!(rich.has_fields());
+		assert!(rich.is_setuid() && !rich.is_setgid() && !rich.is_sticky());
+		let item = rich.to_item().unwrap();
+		assert_eq!(
+			item.len(),
+			2 + 3 * (1 + 8 + 4)
+				+ 2 * (1 + 4)
+				+ (1 + 2 + 5)
+				+ (1 + 2 + 5)
+				+ (1 + 2 + 8 + 4 + 4)
+				+ (1 + 2 + 8)
+		);
+		assert_eq!(Attributes::from_item(&item).unwrap(), rich);
+
+		// truncated fields fail too
+		assert!(Attributes::from_item(&item[..item.len() - 1]).is_err());
+
+		// unknown tags fail rather than being skipped
+		assert!(Attributes::from_item(&[0xA4, 0x01, 0xEE, 0x00]).is_err());
+	}
+
+	#[test]
+	fn timestamps() {
+		use std::time::{Duration, UNIX_EPOCH};
+
+		let after = UNIX_EPOCH + Duration::new(10, 5);
+		assert_eq!(Timestamp::from(after), Timestamp { secs: 10, nanos: 5 });
+		assert_eq!(Timestamp::from(after).to_system_time(), Some(after));
+
+		let before = UNIX_EPOCH - Duration::from_millis(1500);
+		assert_eq!(
+			Timestamp::from(before),
+			Timestamp {
+				secs: -2,
+				nanos: 500_000_000
+			}
+		);
+		assert_eq!(Timestamp::from(before).to_system_time(), Some(before));
+		assert!(Timestamp::from(before) < Timestamp::from(after));
+	}
+
+	#[test]
+	fn timestamp_extremes() {
+		for secs in [i64::MIN, i64::MIN + 1, -1, 0, i64::MAX - 1, i64::MAX] {
+			for nanos in [0, 999_999_999, u32::MAX] {
+				// out of range is None, never a panic
+				let _ = Timestamp { secs, nanos }.to_system_time();
+			}
+		}
+		assert_eq!(
+			Timestamp {
+				secs: i64::MAX,
+				nanos: u32::MAX
+			}
+			.to_system_time(),
+			None
+		);
+
+		let mut item = vec![0xA4, 0x01, 0x01];
+		item.extend(i64::MAX.to_le_bytes());
+		item.extend(999_999_999_u32.to_le_bytes());
+		let attrs = Attributes::from_item(&item).unwrap();
+		assert_eq!(
+			attrs.mtime,
+			Some(Timestamp {
+				secs: i64::MAX,
+				nanos: 999_999_999
+			})
+		);
+
+		let len = item.len();
+		item[len - 4..].copy_from_slice(&1_000_000_000_u32.to_le_bytes());
+		assert!(Attributes::from_item(&item).is_err());
+	}
+
+	#[test]
+	fn segment_validity() {
+		assert!(PathSeg::from_name("a").is_valid());
+		assert!(PathSeg::from_name("").is_valid());
+		assert!(!PathSeg::from_name(b"a\0b").is_valid());
+		assert!(!PathSeg::Segment(b"a".to_vec()).is_valid());
+		assert!(!PathSeg::Segment(Vec::new()).is_valid());
+		assert!(!PathSeg::unc("server", b"sh\0are").is_valid());
+		assert!(PathSeg::url("https", "example.com").is_valid());
+		assert!(PathSeg::Root.is_valid());
+	}
+
+	#[test]
+	fn path_conversions() {
+		let path = Path::from("/usr//./lib/../bin/");
+		assert_eq!(
+			path.segments(),
+			&[
+				PathSeg::Root,
+				PathSeg::Segment(b"usr\0".to_vec()),
+				PathSeg::Segment(b"lib\0".to_vec()),
+				PathSeg::Segment(b"..\0".to_vec()),
+				PathSeg::Segment(b"bin\0".to_vec()),
+			]
+		);
+		assert_eq!(path.to_string(), "/usr/lib/../bin");
+		assert_eq!(
+			path.iter().filter_map(PathSeg::name).collect::<Vec<_>>(),
+			vec![&b"usr"[..], b"lib", b"..", b"bin"]
+		);
+
+		let relative = Path::from_names(&[&b"a"[..], b"b c"]);
+		assert_eq!(relative, Path::from("a/b c"));
+		assert_eq!(relative.to_string(), "a/b c");
+		assert_eq!(Path::from(std::path::Path::new("./a/b c")), relative);
+		assert_eq!(relative.to_path_buf(), PathBuf::from("a").join("b c"));
+		assert_eq!(Path::from("").segments(), &[]);
+		assert_eq!(Path::new(vec![PathSeg::Root]).to_string(), "/");
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::ffi::OsStrExt;
+			let invalid = std::path::Path::new(std::ffi::OsStr::from_bytes(b"/tmp/\xFF\xFE"));
+			let path = Path::from(invalid);
+			assert_eq!(path.segments()[2], PathSeg::Segment(b"\xFF\xFE\0".to_vec()));
+			assert_eq!(path.to_path_buf(), invalid);
+			assert_eq!(path.to_string(), "/tmp/\u{FFFD}\u{FFFD}");
+		}
+	}
+
+	#[test]
+	fn windows_paths() {
+		let name = PathSeg::from_name;
+		let cases = vec![
+			(
+				r"C:\Windows\System32",
+				vec![
+					PathSeg::Drive(b'C'),
+					PathSeg::Root,
+					name("Windows"),
+					name("System32"),
+				],
+			),
+			(
+				r"d:relative\.\file",
+				vec![PathSeg::Drive(b'D'), name("relative"), name("file")],
+			),
+			(
+				r"\rooted/mixed\",
+				vec![PathSeg::Root, name("rooted"), name("mixed")],
+			),
+			(
+				r"\\server\share\dir",
+				vec![PathSeg::unc("server", "share"), PathSeg::Root, name("dir")],
+			),
+			(
+				r"//server/share",
+				vec![PathSeg::unc("server", "share"), PathSeg::Root],
+			),
+			(
+				r"\\?\C:\very\long",
+				vec![
+					PathSeg::Drive(b'C'),
+					PathSeg::Root,
+					name("very"),
+					name("long"),
+				],
+			),
+			(
+				r"\\?\UNC\server\share\x",
+				vec![PathSeg::unc("server", "share"), PathSeg::Root, name("x")],
+			),
+			(r"..\up", vec![name(".."), name("up")]),
+		];
+
+		for (windows, segments) in cases {
+			assert_eq!(
+				Path::from_windows(windows),
+				Path::new(segments),
+				"{}",
+				windows
+			);
+		}
+
+		let unc = Path::from_windows(r"\\server\share\dir");
+		assert_eq!(unc.to_string(), "//server/share/dir");
+		let bytes = unc.to_bytes().unwrap();
+		let (rest, read) = Path::from_bytes((&bytes, 0)).unwrap();
+		assert!(rest.0.is_empty());
+		assert_eq!(read, unc);
+	}
+
+	#[test]
+	fn url_paths() {
+		let url = Path::from_url("https://example.com:8080/a/b.txt").unwrap();
+		assert_eq!(
+			url.segments(),
+			&[
+				PathSeg::url("https", "example.com:8080"),
+				PathSeg::Root,
+				PathSeg::from_name("a"),
+				PathSeg::from_name("b.txt"),
+			]
+		);
+		assert_eq!(url.to_string(), "https://example.com:8080/a/b.txt");
+		assert_eq!(
+			Path::from_url("file:///etc").unwrap().segments(),
+			&[
+				PathSeg::url("file", ""),
+				PathSeg::Root,
+				PathSeg::from_name("etc")
+			]
+		);
+		assert_eq!(
+			Path::from_url("s3://bucket").unwrap().segments(),
+			&[PathSeg::url("s3", "bucket")]
+		);
+		assert!(Path::from_url("/not/a/url").is_none());
+		assert!(Path::from_url("1http://nope").is_none());
+
+		let (_, read) = Path::from_bytes((&url.to_bytes().unwrap(), 0)).unwrap();
+		assert_eq!(read, url);
+	}
+
 	#[test]
 	fn single_file_raw_lowlevel() {
 		let mut ctnr = Vec::new();
@@ -599,7 +1811,7 @@ mod tests {
 			.iter()
 			.find(|indic| indic.kind == IndicKind::Paths)
 			.unwrap();
-		let paths_entry = value.entries.from_offset(paths_indic.offset).unwrap();
+		let paths_entry = value.entries.by_offset(paths_indic.offset).unwrap();
 		assert_eq!(paths_entry.header.encoding, Encoding::Raw);
 		let ((rest, _), pathsv) = PathsEntry::from_bytes((&paths_entry.data, 0)).unwrap();
 		assert_eq!(rest.len(), 0, "remaining data on paths entry");
@@ -616,18 +1828,18 @@ mod tests {
 			.iter()
 			.find(|indic| indic.kind == IndicKind::Attributes)
 			.unwrap();
-		let attrs_entry = value.entries.from_offset(attrs_indic.offset).unwrap();
+		let attrs_entry = value.entries.by_offset(attrs_indic.offset).unwrap();
 		assert_eq!(attrs_entry.header.encoding, Encoding::Raw);
 		let ((rest, _), attrsv) = AttributesEntry::from_bytes((&attrs_entry.data, 0)).unwrap();
 		assert_eq!(rest.len(), 0, "remaining data on attrs entry");
-		assert_eq!(attrsv.attrs, vec![Attributes { mode: 0o644 }]);
+		assert_eq!(attrsv.attrs, vec![TestAttributes { mode: 0o644 }]);
 
 		let file_indic = value
 			.index
 			.iter()
 			.find(|indic| indic.kind == IndicKind::File)
 			.unwrap();
-		let file_entry = value.entries.from_offset(file_indic.offset).unwrap();
+		let file_entry = value.entries.by_offset(file_indic.offset).unwrap();
 		assert_eq!(file_entry.header.encoding, Encoding::Raw);
 		assert_eq!(file_entry.data, b"Hello world!".to_vec());
 	}
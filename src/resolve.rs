@@ -0,0 +1,137 @@
+use crate::{
+	parsers::{IndicKind, Mode, Path, Timestamp},
+	IndexedPath,
+};
+use std::collections::BTreeMap;
+
+/// The view of every path across all loaded containers, after applying catting modes.
+///
+/// Each path maps to the single indic that wins for it. Obtained from [`Tomo::resolve`].
+///
+/// Each container has its own [`Mode`], from its header or as overridden with
+/// [`Tomo::set_mode_override`]. The modes are:
+///
+/// - [`Mode::Stacked`]: the last candidate for a path wins, so that catting `b` after `a` reads
+///   as an update of `a` by `b`.
+/// - [`Mode::Overlay`]: as Stacked, and additionally [`IndicKind::Whiteout`] hides its path and
+///   everything under it from earlier containers, while [`IndicKind::Opaque`] hides everything
+///   under its path from earlier containers.
+/// - [`Mode::Primacy`]: the first candidate for a path wins.
+/// - [`Mode::Newest`]: the candidate with the most recent mtime (see
+///   [`Attributes`](crate::parsers::Attributes)) wins. A missing mtime is older than any.
+///
+/// When containers of different modes define the same path, they're combined like so.
+/// Candidates are offered in load order: sources in the order they were loaded, containers in the
+/// order they appear in their source, and indics in the order they appear in their index. For
+/// each candidate:
+///
+/// 1. If it's a Whiteout or Opaque marker from an Overlay container, it hides paths from earlier
+///    containers, except those from Primacy containers. Markers from containers of any other mode
+///    are ignored. Markers are never winners themselves.
+/// 2. Otherwise, if there's no current winner for its path, or the current winner is from the
+///    same container, the candidate wins: within a single container, the last indic always wins.
+/// 3. Otherwise, if either the current winner or the candidate is from a Primacy container, the
+///    current winner stays. A Primacy container can't be overridden, and can only add paths.
+/// 4. Otherwise, if either is from a Newest container, the candidate wins if its mtime is at
+///    least as recent as the current winner's.
+/// 5. Otherwise (both are Stacked or Overlay), the candidate wins.
+///
+/// So precedence goes Primacy, then Newest, then Stacked and Overlay, whichever side of the
+/// comparison the mode is on. As this only depends on load order, the result is deterministic.
+///
+/// [`Tomo::resolve`]: crate::Tomo::resolve
+/// [`Tomo::set_mode_override`]: crate::Tomo::set_mode_override
+#[derive(Clone, Debug, Default)]
+pub struct Resolved {
+	paths: BTreeMap<Path, Winner>,
+}
+
+#[derive(Clone, Debug)]
+struct Winner {
+	/// The mode of the container the winner is from.
+	mode: Mode,
+	indexed: IndexedPath,
+}
+
+fn same_container(a: &IndexedPath, b: &IndexedPath) -> bool {
+	(a.source, a.container) == (b.source, b.container)
+}
+
+fn mtime(indexed: &IndexedPath) -> Option<Timestamp> {
+	indexed.attrs.as_ref().and_then(|attrs| attrs.mtime)
+}
+
+/// Whether a candidate from a later container beats the current winner.
+fn beats(winner: &Winner, mode: Mode, candidate: &IndexedPath) -> bool {
+	if winner.mode == Mode::Primacy || mode == Mode::Primacy {
+		false
+	} else if winner.mode == Mode::Newest || mode == Mode::Newest {
+		mtime(candidate) >= mtime(&winner.indexed)
+	} else {
+		true
+	}
+}
+
+impl Resolved {
+	/// Offer a candidate, from a container with the given mode.
+	pub(crate) fn offer(&mut self, mode: Mode, candidate: IndexedPath) {
+		match (mode, candidate.indic.kind) {
+			(Mode::Overlay, IndicKind::Whiteout) => self.hide(&candidate, true),
+			(Mode::Overlay, IndicKind::Opaque) => self.hide(&candidate, false),
+			(_, IndicKind::Whiteout) | (_, IndicKind::Opaque) => {}
+			(mode, _) => {
+				if let Some(winner) = self.paths.get(&candidate.path) {
+					if !same_container(&winner.indexed, &candidate)
+						&& !beats(winner, mode, &candidate)
+					{
+						return;
+					}
+				}
+
+				self.paths.insert(
+					candidate.path.clone(),
+					Winner {
+						mode,
+						indexed: candidate,
+					},
+				);
+			}
+		}
+	}
+
+	/// Remove everything under the marker's path that comes from other (earlier) containers.
+	///
+	/// Paths from Primacy containers are kept.
+	fn hide(&mut self, marker: &IndexedPath, itself: bool) {
+		self.paths.retain(|path, winner| {
+			winner.mode == Mode::Primacy
+				|| same_container(&winner.indexed, marker)
+				|| !path.starts_with(&marker.path)
+				|| (path == &marker.path && !itself)
+		});
+	}
+
+	/// Keep only the paths for which the predicate is true.
+	pub(crate) fn retain(&mut self, mut keep: impl FnMut(&Path) -> bool) {
+		self.paths.retain(|path, _| keep(path));
+	}
+
+	/// The winning indic for a path, if it exists.
+	pub fn get(&self, path: &Path) -> Option<&IndexedPath> {
+		self.paths.get(path).map(|winner| &winner.indexed)
+	}
+
+	/// Iterate through the winning indics, in path order.
+	pub fn iter(&self) -> impl Iterator<Item = &IndexedPath> + '_ {
+		self.paths.values().map(|winner| &winner.indexed)
+	}
+
+	/// The amount of paths.
+	pub fn len(&self) -> usize {
+		self.paths.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.paths.is_empty()
+	}
+}
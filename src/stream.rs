@@ -1,7 +1,9 @@
+pub use entries::{EntriesStream, Entry};
 pub use index::IndexStream;
+pub use indexed::{IndexedPath, IndexedPathsStream};
 pub use paths::PathsStream;
-// pub use entries::EntriesStream;
 
+pub mod entries;
 pub mod index;
+pub mod indexed;
 pub mod paths;
-// pub mod entries;
@@ -0,0 +1,77 @@
+use crate::{entry::EntryReader, parsers::Indic, SourceState, TomoError};
+
+/// An entry, with the indic that points to it and a reader over its data.
+#[derive(Debug)]
+pub struct Entry<'a> {
+	pub indic: Indic,
+	pub reader: EntryReader<'a>,
+}
+
+/// Goes through every entry of a container, in index order.
+///
+/// This can't be a [`Stream`](futures::stream::Stream), as each [`Entry`] borrows the source to
+/// read its data from. Instead, call [`EntriesStream::next`] in a loop:
+///
+/// ```
+/// # #[async_std::main]
+/// # async fn main() -> Result<(), tomo::prelude::TomoError> {
+/// # use futures::io::Cursor;
+/// # let mut output = Cursor::new(Vec::new());
+/// # tomo::TomoWriter::default().finish(&mut output).await?;
+/// # let mut source = Cursor::new(output.into_inner());
+/// use futures::io::AsyncReadExt;
+/// use tomo::prelude::*;
+///
+/// let mut tomo = Tomo::default();
+/// let (ss, _) = tomo.load_one(Seekable::new(&mut source)).await?;
+/// let mut entries = ss.entries(0).unwrap();
+/// while let Some(entry) = entries.next().await {
+///     let mut entry = entry?;
+///     let mut data = Vec::new();
+///     entry.reader.read_to_end(&mut data).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Indics without data (like directories) are skipped. Entries don't need to be read to the end
+/// before moving on to the next, and the data of entries that aren't read is skipped over.
+#[derive(Debug)]
+pub struct EntriesStream<'src, 's> {
+	source: &'src mut SourceState<'s>,
+	container: usize,
+	next: u64,
+}
+
+impl<'src, 's> EntriesStream<'src, 's> {
+	pub(crate) fn new(source: &'src mut SourceState<'s>, container: usize) -> Self {
+		Self {
+			source,
+			container,
+			next: 0,
+		}
+	}
+
+	/// Read the header of the next entry and return it along with a reader over its data.
+	#[allow(clippy::should_implement_trait)]
+	pub async fn next(&mut self) -> Option<Result<Entry<'_>, TomoError>> {
+		let indic = loop {
+			match self.source.indic(self.container, self.next).await {
+				Err(err) => return Some(Err(err)),
+				Ok(None) => return None,
+				Ok(Some(indic)) => {
+					self.next += 1;
+					if indic.length > 0 {
+						break indic;
+					}
+				}
+			}
+		};
+
+		Some(
+			EntryReader::new(self.source, self.container, &indic)
+				.await
+				.map(|reader| Entry { indic, reader }),
+		)
+	}
+}
@@ -0,0 +1,133 @@
+use crate::{
+	parsers::{Attributes, Indic, Path},
+	Tomo, TomoError,
+};
+use futures::{
+	stream::Stream,
+	task::{Context, Poll},
+	Future,
+};
+use std::{collections::HashMap, pin::Pin};
+
+/// An indic along with its path and attributes, and where it was found.
+#[derive(Clone, Debug)]
+pub struct IndexedPath {
+	/// The source, by the order it was loaded in.
+	pub source: usize,
+	/// The container, by its order in the source.
+	pub container: usize,
+	/// The (0-indexed) position of the indic in the container's index.
+	pub position: u64,
+	pub indic: Indic,
+	pub path: Path,
+	pub attrs: Option<Attributes>,
+}
+
+pub struct IndexedPathsStream<'tomo, 's> {
+	tomo: &'tomo mut Tomo<'s>,
+
+	source: usize,
+	container: usize,
+	indic_in_container: u64,
+
+	// attributes are deduplicated by writers, so many indics tend to share the same few
+	attrs_cache: HashMap<u32, Attributes>,
+}
+
+impl<'tomo, 's> IndexedPathsStream<'tomo, 's> {
+	pub(crate) fn new(tomo: &'tomo mut Tomo<'s>) -> Self {
+		Self {
+			tomo,
+
+			source: 0,
+			container: 0,
+			indic_in_container: 0,
+
+			attrs_cache: HashMap::new(),
+		}
+	}
+
+	fn next_container(&mut self) {
+		self.container += 1;
+		self.indic_in_container = 0;
+		self.attrs_cache.clear();
+	}
+
+	async fn async_poll(&mut self) -> Option<Result<IndexedPath, TomoError>> {
+		let ret: Result<Option<IndexedPath>, TomoError> = async {
+			'retry: loop {
+				let source = match self.tomo.sources.get_mut(self.source) {
+					Some(source) => source,
+					None => break Ok(None),
+				};
+
+				if self.container >= source.len() {
+					self.source += 1;
+					self.container = 0;
+					continue 'retry;
+				}
+
+				let indic = match source
+					.indic(self.container, self.indic_in_container)
+					.await?
+				{
+					Some(indic) => indic,
+					None => {
+						self.next_container();
+						continue 'retry;
+					}
+				};
+				self.indic_in_container += 1;
+
+				if indic.path == 0 {
+					continue 'retry;
+				}
+
+				let path = match source.paths_table(self.container).await? {
+					Some(table) => table.path(source, indic.path).await?,
+					None => return Err(TomoError::MissingItem(indic.path)),
+				};
+
+				let tables = source.tables(self.container).await?;
+				let attrs = match (indic.attrs, &tables.attrs) {
+					(0, _) => None,
+					(n, Some(table)) => Some(match self.attrs_cache.get(&n) {
+						Some(attrs) => attrs.clone(),
+						None => {
+							let attrs = table.attributes(source, n).await?;
+							self.attrs_cache.insert(n, attrs.clone());
+							attrs
+						}
+					}),
+					(n, None) => return Err(TomoError::MissingItem(n)),
+				};
+
+				break Ok(Some(IndexedPath {
+					source: self.source,
+					container: self.container,
+					position: self.indic_in_container - 1,
+					indic,
+					path,
+					attrs,
+				}));
+			}
+		}
+		.await;
+
+		// errors are not recoverable, so stop there rather than yield the same error forever
+		if ret.is_err() {
+			self.source = self.tomo.sources.len();
+		}
+
+		ret.transpose()
+	}
+}
+
+impl<'tomo, 's> Stream for IndexedPathsStream<'tomo, 's> {
+	type Item = Result<IndexedPath, TomoError>;
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let mut fut =
+			Box::pin(self.async_poll()) as Pin<Box<dyn Future<Output = Option<Self::Item>>>>;
+		Future::poll(fut.as_mut(), cx)
+	}
+}
@@ -0,0 +1,215 @@
+use crate::{
+	checksum::Digests,
+	entry::EntryReader,
+	lookup::ChecksumTable,
+	parsers::{ChecksumAlgorithm, Indic, IndicKind, Integrity, Path, CONTAINER_HEADER_SIZE},
+	SourceState, Tomo, TomoError,
+};
+use deku::DekuContainerRead;
+use futures::io::{copy, sink, AsyncReadExt};
+
+/// How much to read at once when digesting a container's index and metadata.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// The outcome of [`Tomo::verify`].
+#[derive(Debug, Default)]
+pub struct Verification {
+	/// How many entries were checked.
+	pub checked: u64,
+
+	/// How many containers have no Checksums entry, so weren't checked at all.
+	pub unchecked_containers: usize,
+
+	/// Every entry that didn't check out, in load order.
+	pub corrupt: Vec<CorruptEntry>,
+}
+
+impl Verification {
+	/// Whether nothing was found to be corrupt.
+	pub fn is_ok(&self) -> bool {
+		self.corrupt.is_empty()
+	}
+}
+
+/// An entry whose data doesn't match its checksums.
+#[derive(Debug)]
+pub struct CorruptEntry {
+	/// The source, by the order it was loaded in.
+	pub source: usize,
+	/// The container, by its order in the source.
+	pub container: usize,
+	/// The (0-indexed) position of the indic in the container's index.
+	pub position: u64,
+	pub indic: Indic,
+	/// The path of the indic, if it has one and it could be read.
+	pub path: Option<Path>,
+	pub problem: Corruption,
+}
+
+/// What's wrong with a [`CorruptEntry`].
+#[derive(Debug)]
+pub enum Corruption {
+	/// The data was read in full, but doesn't match the digest for this algorithm.
+	Mismatch(ChecksumAlgorithm),
+
+	/// The data couldn't be read or decoded, e.g. because compressed data was damaged.
+	Unreadable(TomoError),
+}
+
+impl<'s> Tomo<'s> {
+	/// Check the entry data of every loaded container against their checksums.
+	///
+	/// Reads every entry which has checksums in full, and reports exactly which don't match or
+	/// can't be read. Entries of containers without a Checksums entry can't be checked, and are
+	/// counted in [`Verification::unchecked_containers`].
+	///
+	/// Fails if the Checksums entry itself can't be read, or refers to indics that aren't in the
+	/// index.
+	pub async fn verify(&mut self) -> Result<Verification, TomoError> {
+		let mut verification = Verification::default();
+		for (s, source) in self.sources.iter_mut().enumerate() {
+			for container in 0..source.len() {
+				let table = match ChecksumTable::find_in(source, container).await? {
+					Some(table) => table,
+					None => {
+						verification.unchecked_containers += 1;
+						continue;
+					}
+				};
+
+				let records = table.all(source).await?;
+				for expected in records.chunk_by(|a, b| a.indic == b.indic) {
+					let position = expected[0].indic;
+					let indic = source
+						.indic(container, position)
+						.await?
+						.ok_or(TomoError::MissingIndic(position))?;
+
+					let checked = async {
+						let reader = EntryReader::new(source, container, &indic).await?;
+						copy(reader.verify(position, expected.to_vec()), &mut sink())
+							.await
+							.map_err(TomoError::from_io)
+					}
+					.await;
+					verification.checked += 1;
+
+					let problem = match checked {
+						Ok(_) => continue,
+						Err(TomoError::ChecksumMismatch { algorithm, .. }) => {
+							Corruption::Mismatch(algorithm)
+						}
+						Err(err) => Corruption::Unreadable(err),
+					};
+
+					// the Paths entry may be what's corrupt
+					let path = match source.indexed(container, position).await {
+						Ok(found) => found.map(|found| found.path),
+						Err(_) => None,
+					};
+
+					verification.corrupt.push(CorruptEntry {
+						source: s,
+						container,
+						position,
+						indic,
+						path,
+						problem,
+					});
+				}
+			}
+		}
+
+		Ok(verification)
+	}
+}
+
+impl SourceState<'_> {
+	/// Check a container's Integrity entry, if it has one.
+	///
+	/// It does if its header says so, or if its first indic is an Integrity indic: either way, a
+	/// single damaged bit can't make the check be skipped. Digests the header, the index, and the
+	/// metadata entries as stored, after checking that those entries are within the container.
+	/// Fails with [`TomoError::CorruptContainer`] if anything doesn't check out, including if the
+	/// container is cut short.
+	pub(crate) async fn check_integrity(&mut self, container: usize) -> Result<(), TomoError> {
+		let start = self.container_start(container);
+		let corrupt = |err: TomoError| match err {
+			TomoError::Parse(_) | TomoError::UnexpectedEof { .. } => {
+				TomoError::CorruptContainer { offset: start }
+			}
+			other => other,
+		};
+
+		let flagged = self.headers[container].1.integrity;
+		let integrity = match self.indic(container, 0).await.map_err(corrupt)? {
+			Some(indic) if indic.kind == IndicKind::Integrity => indic,
+			_ if flagged => return Err(TomoError::CorruptContainer { offset: start }),
+			_ => return Ok(()),
+		};
+
+		// other metadata indics come next
+		let mut metadata = Vec::new();
+		let mut n = 1;
+		while let Some(indic) = self.indic(container, n).await.map_err(corrupt)? {
+			if indic.path != 0 {
+				break;
+			}
+			metadata.push(indic);
+			n += 1;
+		}
+
+		let entries_bytes = self.headers[container].1.entries_bytes;
+		let within = |indic: &Indic| {
+			indic
+				.offset
+				.checked_add(indic.length)
+				.is_some_and(|end| end <= entries_bytes)
+		};
+		if !within(&integrity) || !metadata.iter().all(within) {
+			return Err(TomoError::CorruptContainer { offset: start });
+		}
+
+		let mut data = Vec::new();
+		EntryReader::without_dictionary(self, container, &integrity)
+			.await
+			.map_err(corrupt)?
+			.read_to_end(&mut data)
+			.await
+			.map_err(|err| corrupt(TomoError::from_io(err)))?;
+		let (_, expected) = Integrity::from_bytes((&data, 0)).map_err(|err| corrupt(err.into()))?;
+
+		let mut digests = Digests::new(Some(expected.algorithm));
+		let head = CONTAINER_HEADER_SIZE as u64 + self.headers[container].1.index_bytes;
+		self.digest_range(&mut digests, start, head)
+			.await
+			.map_err(corrupt)?;
+		for indic in metadata {
+			let entry_start = self.entries_start(container) + indic.offset;
+			self.digest_range(&mut digests, entry_start, indic.length)
+				.await
+				.map_err(corrupt)?;
+		}
+
+		match digests.finish().first() {
+			Some((_, digest)) if digest == &expected.digest => Ok(()),
+			_ => Err(TomoError::CorruptContainer { offset: start }),
+		}
+	}
+
+	async fn digest_range(
+		&mut self,
+		digests: &mut Digests,
+		start: u64,
+		len: u64,
+	) -> Result<(), TomoError> {
+		self.seek_to(start).await?;
+		let mut left = len;
+		while left > 0 {
+			let chunk = self.read(left.min(CHUNK_SIZE)).await?;
+			digests.update(&chunk);
+			left -= chunk.len() as u64;
+		}
+		Ok(())
+	}
+}
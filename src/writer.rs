@@ -0,0 +1,840 @@
+use crate::{
+	checksum::{Digests, Hashing},
+	parsers::{
+		Attributes, Checksum, ChecksumAlgorithm, ContainerHeader, Device, Encoding, EntryHeader,
+		HardlinkTarget, Indic, IndicKind, Integrity, Lookup, Mode, Path, PathSeg, ZstdParams,
+		CHECKSUM_SIZE, CONTAINER_HEADER_SIZE, DIGEST_SIZE, INDIC_SIZE, INTEGRITY_SIZE, LOOKUP_SIZE,
+	},
+	TomoError,
+};
+use async_compression::{futures::bufread::ZstdEncoder, Level};
+use deku::prelude::*;
+use futures::{
+	io::{copy, AllowStdIo, BufReader, Cursor},
+	AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt,
+};
+use std::{collections::BTreeMap, fmt, fs::File, io::SeekFrom, path::PathBuf};
+
+pub use pack::PackOptions;
+
+mod pack;
+
+/// Paths and attributes are referenced from indics with 24-bit numbers.
+pub(crate) const MAX_ITEMS: usize = 0xFF_FFFF;
+
+/// Files up to this size are compressed with the dictionary, unless configured otherwise.
+pub const DEFAULT_DICTIONARY_THRESHOLD: u64 = 128 * 1024;
+
+/// How entries are encoded when written.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Compression {
+	/// Store entries as-is.
+	#[default]
+	None,
+
+	/// Compress entries with zstd, at the given level.
+	Zstd { level: i32 },
+}
+
+/// Writes a single container.
+///
+/// Files and directories are queued with [`TomoWriter::add_file`] and [`TomoWriter::add_dir`], and
+/// nothing is written until [`TomoWriter::finish`] is called. At that point the writer knows how
+/// many indics there will be, so it can reserve space for the header and index, write out the Paths
+/// and Attributes entries, stream each file's data in turn, and finally seek back to fill in the
+/// header and index. Only metadata is ever held in memory, except when training a dictionary (see
+/// [`TomoWriter::set_dictionary_training`]).
+///
+/// ```
+/// # #[async_std::main]
+/// # async fn main() -> Result<(), tomo::prelude::TomoError> {
+/// # use futures::io::Cursor;
+/// use tomo::{parsers::{Attributes, Path}, TomoWriter};
+/// let path = Path::from("hello");
+/// let attrs = Attributes::new(0o644);
+///
+/// let mut writer = TomoWriter::default();
+/// writer.add_file(path, Some(attrs), &b"Hello world!"[..]);
+///
+/// let mut output = Cursor::new(Vec::new());
+/// writer.finish(&mut output).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TomoWriter<'w> {
+	mode: Mode,
+	compression: Compression,
+	metadata_compression: Compression,
+	dictionary: Option<Vec<u8>>,
+	dictionary_training: Option<usize>,
+	dictionary_threshold: u64,
+	reverse_paths: bool,
+	checksums: Vec<ChecksumAlgorithm>,
+	integrity: Option<ChecksumAlgorithm>,
+	items: Vec<Item<'w>>,
+}
+
+impl Default for TomoWriter<'_> {
+	fn default() -> Self {
+		Self {
+			mode: Mode::default(),
+			compression: Compression::default(),
+			metadata_compression: Compression::default(),
+			dictionary: None,
+			dictionary_training: None,
+			dictionary_threshold: DEFAULT_DICTIONARY_THRESHOLD,
+			reverse_paths: true,
+			checksums: vec![ChecksumAlgorithm::Blake3],
+			integrity: Some(ChecksumAlgorithm::Blake3),
+			items: Vec::new(),
+		}
+	}
+}
+
+struct Item<'w> {
+	kind: IndicKind,
+	path: Path,
+	attrs: Option<Attributes>,
+	data: Option<Data<'w>>,
+}
+
+/// File data, possibly read ahead to find out whether it's small enough for the dictionary.
+enum Data<'w> {
+	/// Not read from yet.
+	Unread(Box<dyn AsyncRead + Unpin + 'w>),
+
+	/// On disk, and only opened when needed.
+	OnDisk(PathBuf),
+
+	/// Read in full, and no larger than the dictionary threshold.
+	Small(Vec<u8>),
+
+	/// Larger than the dictionary threshold, with the bytes read ahead put back in front.
+	Large(Box<dyn AsyncRead + Unpin + 'w>),
+
+	/// Written as-is, without compression.
+	Raw(Vec<u8>),
+
+	/// A hardlink to the first non-hardlink item with that path.
+	Hardlink(Path),
+}
+
+impl<'w> Data<'w> {
+	async fn read_ahead(self, threshold: u64) -> Result<Data<'w>, TomoError> {
+		let mut data = match self {
+			Self::Unread(data) => data,
+			Self::OnDisk(path) => Box::new(AllowStdIo::new(File::open(path)?)),
+			other => return Ok(other),
+		};
+
+		let mut ahead = Vec::new();
+		(&mut data)
+			.take(threshold.saturating_add(1))
+			.read_to_end(&mut ahead)
+			.await?;
+
+		Ok(if ahead.len() as u64 <= threshold {
+			Self::Small(ahead)
+		} else {
+			Self::Large(Box::new(Cursor::new(ahead).chain(data)))
+		})
+	}
+}
+
+impl fmt::Debug for TomoWriter<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("TomoWriter")
+			.field("mode", &self.mode)
+			.field("compression", &self.compression)
+			.field("metadata_compression", &self.metadata_compression)
+			.field("dictionary", &self.dictionary.as_ref().map(|d| d.len()))
+			.field("dictionary_training", &self.dictionary_training)
+			.field("dictionary_threshold", &self.dictionary_threshold)
+			.field("reverse_paths", &self.reverse_paths)
+			.field("checksums", &self.checksums)
+			.field("integrity", &self.integrity)
+			.field("items", &self.items.len())
+			.finish()
+	}
+}
+
+impl<'w> TomoWriter<'w> {
+	/// Start a container with the given catting mode.
+	pub fn new(mode: Mode) -> Self {
+		Self {
+			mode,
+			..Self::default()
+		}
+	}
+
+	/// Set how file data is encoded.
+	///
+	/// Compression is streamed: file data is never held in memory in full.
+	pub fn set_compression(&mut self, compression: Compression) {
+		self.compression = compression;
+	}
+
+	/// Set a zstd dictionary to compress file data with.
+	///
+	/// The dictionary is written to the container as its own entry, and every small file
+	/// compressed with zstd (see [`TomoWriter::set_compression`] and
+	/// [`TomoWriter::set_dictionary_threshold`]) references it. This greatly improves compression
+	/// of many small, similar files.
+	pub fn set_dictionary(&mut self, dictionary: Vec<u8>) {
+		self.dictionary = Some(dictionary);
+	}
+
+	/// Train a zstd dictionary of up to `max_size` bytes from the files being packed.
+	///
+	/// Every small file is sampled, so they're all held in memory during [`TomoWriter::finish`].
+	/// If training doesn't succeed, for example because there's too little to learn from, files
+	/// are compressed without a dictionary. A dictionary given with
+	/// [`TomoWriter::set_dictionary`] takes precedence.
+	pub fn set_dictionary_training(&mut self, max_size: usize) {
+		self.dictionary_training = Some(max_size);
+	}
+
+	/// Set the size up to which files are compressed with the dictionary.
+	///
+	/// Larger files gain little from a dictionary, and aren't used for training. Defaults to
+	/// [`DEFAULT_DICTIONARY_THRESHOLD`].
+	pub fn set_dictionary_threshold(&mut self, bytes: u64) {
+		self.dictionary_threshold = bytes;
+	}
+
+	/// Set how the Paths and Attributes entries are encoded.
+	///
+	/// Readers need to decode these in memory in full, instead of seeking through them, so this
+	/// trades off read performance for size.
+	pub fn set_metadata_compression(&mut self, compression: Compression) {
+		self.metadata_compression = compression;
+	}
+
+	/// Set whether to write a ReversePaths entry. Defaults to true.
+	///
+	/// That entry lets readers find a path by reading only what's along it, instead of going
+	/// through every path and indic in the container. It takes about as much space as the Paths
+	/// entry, so it can be left out of containers which will only ever be read in full.
+	pub fn set_reverse_paths(&mut self, enabled: bool) {
+		self.reverse_paths = enabled;
+	}
+
+	/// Set which digests to write in the Checksums entry. Defaults to BLAKE3 only.
+	///
+	/// Every entry, metadata included, gets a digest of its data (before compression) for each
+	/// algorithm. With none, no Checksums entry is written, and readers can't detect corruption.
+	pub fn set_checksums(&mut self, algorithms: &[ChecksumAlgorithm]) {
+		let mut algorithms = algorithms.to_vec();
+		algorithms.sort();
+		algorithms.dedup();
+		self.checksums = algorithms;
+	}
+
+	/// Set which digest to write in the Integrity entry, if any. Defaults to BLAKE3.
+	///
+	/// That digest covers the header, the index, and the metadata entries (Paths, Attributes,
+	/// Checksums, etc), and is checked by readers when loading the container, before they trust
+	/// anything in the index. File data isn't covered: see [`TomoWriter::set_checksums`].
+	pub fn set_integrity(&mut self, algorithm: Option<ChecksumAlgorithm>) {
+		self.integrity = algorithm;
+	}
+
+	/// Queue a file.
+	///
+	/// The data source is only read from during [`TomoWriter::finish`].
+	pub fn add_file(
+		&mut self,
+		path: Path,
+		attrs: Option<Attributes>,
+		data: impl AsyncRead + Unpin + 'w,
+	) {
+		self.items.push(Item {
+			kind: IndicKind::File,
+			path,
+			attrs,
+			data: Some(Data::Unread(Box::new(data))),
+		});
+	}
+
+	/// Queue a file from disk.
+	///
+	/// The file is only opened during [`TomoWriter::finish`], and closed once written, so any
+	/// amount of files can be queued without holding as many open.
+	pub fn add_file_from_disk(
+		&mut self,
+		path: Path,
+		attrs: Option<Attributes>,
+		on_disk: impl Into<PathBuf>,
+	) {
+		self.items.push(Item {
+			kind: IndicKind::File,
+			path,
+			attrs,
+			data: Some(Data::OnDisk(on_disk.into())),
+		});
+	}
+
+	/// Queue a directory.
+	pub fn add_dir(&mut self, path: Path, attrs: Option<Attributes>) {
+		self.items.push(Item {
+			kind: IndicKind::Dir,
+			path,
+			attrs,
+			data: None,
+		});
+	}
+
+	/// Queue a whiteout, which hides a path and everything under it from earlier containers.
+	///
+	/// Only has an effect if the container is in [`Mode::Overlay`].
+	pub fn add_whiteout(&mut self, path: Path) {
+		self.items.push(Item {
+			kind: IndicKind::Whiteout,
+			path,
+			attrs: None,
+			data: None,
+		});
+	}
+
+	/// Queue an opaque directory, which hides everything under it from earlier containers.
+	///
+	/// This is a directory and an [`IndicKind::Opaque`] marker with the same path. The marker only
+	/// has an effect if the container is in [`Mode::Overlay`].
+	pub fn add_opaque_dir(&mut self, path: Path, attrs: Option<Attributes>) {
+		self.add_dir(path.clone(), attrs);
+		self.items.push(Item {
+			kind: IndicKind::Opaque,
+			path,
+			attrs: None,
+			data: None,
+		});
+	}
+
+	/// Queue a symlink, with its target as raw bytes.
+	pub fn add_symlink(&mut self, path: Path, attrs: Option<Attributes>, target: Vec<u8>) {
+		self.items.push(Item {
+			kind: IndicKind::Symlink,
+			path,
+			attrs,
+			data: Some(Data::Raw(target)),
+		});
+	}
+
+	/// Queue a hardlink to another item in this container.
+	///
+	/// The target is the first item (that isn't itself a hardlink) queued with that path, in
+	/// any order. If there's none, [`TomoWriter::finish`] fails with
+	/// [`TomoError::MissingLinkTarget`].
+	pub fn add_hardlink(&mut self, path: Path, attrs: Option<Attributes>, target: Path) {
+		self.items.push(Item {
+			kind: IndicKind::Hardlink,
+			path,
+			attrs,
+			data: Some(Data::Hardlink(target)),
+		});
+	}
+
+	/// Queue a character device.
+	pub fn add_char_device(&mut self, path: Path, attrs: Option<Attributes>, device: Device) {
+		self.add_special(IndicKind::CharDevice, path, attrs, Some(device));
+	}
+
+	/// Queue a block device.
+	pub fn add_block_device(&mut self, path: Path, attrs: Option<Attributes>, device: Device) {
+		self.add_special(IndicKind::BlockDevice, path, attrs, Some(device));
+	}
+
+	/// Queue a named pipe.
+	pub fn add_fifo(&mut self, path: Path, attrs: Option<Attributes>) {
+		self.add_special(IndicKind::Fifo, path, attrs, None);
+	}
+
+	/// Queue a unix socket.
+	pub fn add_socket(&mut self, path: Path, attrs: Option<Attributes>) {
+		self.add_special(IndicKind::Socket, path, attrs, None);
+	}
+
+	fn add_special(
+		&mut self,
+		kind: IndicKind,
+		path: Path,
+		attrs: Option<Attributes>,
+		device: Option<Device>,
+	) {
+		self.items.push(Item {
+			kind,
+			path,
+			attrs,
+			data: device.map(|device| Data::Raw(device.to_bytes().expect("infallible"))),
+		});
+	}
+
+	/// The amount of queued items.
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	/// Write the container out.
+	///
+	/// Writing starts at the current position of the output, which is left at the end of the
+	/// container when this returns. Returns the header that was written.
+	///
+	/// Fails with [`TomoError::InvalidPath`] before writing anything if a path has a segment that
+	/// can't be stored (see [`PathSeg::is_valid`]).
+	pub async fn finish<W: AsyncWrite + AsyncSeek + Unpin>(
+		mut self,
+		output: &mut W,
+	) -> Result<ContainerHeader, TomoError> {
+		if let Some(item) = self
+			.items
+			.iter()
+			.find(|item| !item.path.iter().all(PathSeg::is_valid))
+		{
+			return Err(TomoError::InvalidPath(item.path.clone()));
+		}
+
+		let start = output.seek(SeekFrom::Current(0)).await?;
+
+		if self.items.is_empty() {
+			let header = ContainerHeader {
+				integrity: false,
+				mode: self.mode,
+				index_bytes: 0,
+				entries_bytes: 0,
+			};
+			output.write_all(&header.to_bytes()?).await?;
+			output.flush().await?;
+			return Ok(header);
+		}
+
+		let mut paths = Numbered::default();
+		let mut attrs = Numbered::default();
+		let numbers = self
+			.items
+			.iter()
+			.map(|item| {
+				let path = paths.number(&item.path);
+				let attr = item.attrs.as_ref().map(|a| attrs.number(a)).unwrap_or(0);
+				(path, attr)
+			})
+			.collect::<Vec<_>>();
+
+		if paths.len() > MAX_ITEMS || attrs.len() > MAX_ITEMS {
+			return Err(TomoError::ContainerFull);
+		}
+
+		let paths_entry = paths.entry(|path| path.to_bytes())?;
+		let attrs_entry = if attrs.is_empty() {
+			None
+		} else {
+			let kind = if attrs.items.iter().any(|attr| attr.has_fields()) {
+				IndicKind::AttributesV2
+			} else {
+				IndicKind::Attributes
+			};
+			Some((kind, attrs.entry(|attr| attr.to_item())?))
+		};
+
+		let threshold = self.dictionary_threshold;
+		let dictionary = match (self.compression, self.dictionary.take()) {
+			(Compression::None, _) => None,
+			(Compression::Zstd { .. }, Some(dict)) => Some(dict),
+			(Compression::Zstd { .. }, None) => match self.dictionary_training {
+				Some(max_size) => train(&mut self.items, threshold, max_size).await?,
+				None => None,
+			},
+		};
+		let dictionary = dictionary.as_deref();
+
+		let special = 1
+			+ attrs_entry.iter().count()
+			+ dictionary.iter().count()
+			+ usize::from(self.reverse_paths)
+			+ usize::from(!self.checksums.is_empty())
+			+ self.integrity.iter().count();
+		let index_bytes = (special + self.items.len()) as u64 * INDIC_SIZE;
+		output
+			.write_all(&vec![0; CONTAINER_HEADER_SIZE + index_bytes as usize])
+			.await?;
+
+		// hardlinks point at indics by position, which is only known now
+		let mut targets = BTreeMap::new();
+		for (n, item) in self.items.iter().enumerate() {
+			if item.kind != IndicKind::Hardlink {
+				targets.entry(&item.path).or_insert((special + n) as u64);
+			}
+		}
+		let links = self
+			.items
+			.iter()
+			.map(|item| match &item.data {
+				Some(Data::Hardlink(target)) => match targets.get(target) {
+					Some(indic) => Ok(Some(HardlinkTarget { indic: *indic }.to_bytes()?)),
+					None => Err(TomoError::MissingLinkTarget),
+				},
+				_ => Ok(None),
+			})
+			.collect::<Result<Vec<_>, TomoError>>()?;
+
+		let mut index = Vec::with_capacity(special + self.items.len());
+		let mut offset = 0;
+
+		// digests of each entry's data, keyed by indic position
+		let algorithms = self.checksums;
+		let mut checksums = Vec::new();
+		let mut record = |indic: usize, digests: Vec<(ChecksumAlgorithm, [u8; DIGEST_SIZE])>| {
+			checksums.extend(digests.into_iter().map(|(algorithm, digest)| Checksum {
+				indic: indic as u64,
+				algorithm,
+				digest,
+			}));
+		};
+
+		// metadata entries as stored, for the Integrity digest, which is always the first indic
+		// but also covers the index, so it's filled in last
+		let mut metadata = Vec::new();
+		if self.integrity.is_some() {
+			index.push(Indic::new(IndicKind::Integrity, 0, 0, 0, 0));
+		}
+
+		let length = write_metadata(
+			output,
+			self.metadata_compression,
+			&paths_entry,
+			&mut metadata,
+		)
+		.await?;
+		record(index.len(), Digests::of(&algorithms, &paths_entry));
+		index.push(Indic::new(IndicKind::Paths, 0, 0, offset, length));
+		offset += length;
+
+		if let Some((kind, attrs_entry)) = attrs_entry {
+			let length = write_metadata(
+				output,
+				self.metadata_compression,
+				&attrs_entry,
+				&mut metadata,
+			)
+			.await?;
+			record(index.len(), Digests::of(&algorithms, &attrs_entry));
+			index.push(Indic::new(kind, 0, 0, offset, length));
+			offset += length;
+		}
+
+		let dictionary = match dictionary {
+			Some(dict) => {
+				let length =
+					write_metadata(output, self.metadata_compression, dict, &mut metadata).await?;
+				let number = index.len() as u64;
+				record(index.len(), Digests::of(&algorithms, dict));
+				index.push(Indic::new(IndicKind::Dictionary, 0, 0, offset, length));
+				offset += length;
+				Some((number, dict))
+			}
+			None => None,
+		};
+
+		if self.reverse_paths {
+			let mut tree = ReverseNode::default();
+			for (n, item) in self.items.iter().enumerate() {
+				tree.insert(item.path.segments(), (special + n) as u64);
+			}
+
+			let mut reverse_entry = Vec::new();
+			tree.serialise(&mut reverse_entry)?;
+			let length = write_metadata(
+				output,
+				self.metadata_compression,
+				&reverse_entry,
+				&mut metadata,
+			)
+			.await?;
+			record(index.len(), Digests::of(&algorithms, &reverse_entry));
+			index.push(Indic::new(IndicKind::ReversePaths, 0, 0, offset, length));
+			offset += length;
+		}
+
+		// its data is only known once everything else is written, so it's filled in last
+		let checksums_at = if algorithms.is_empty() {
+			None
+		} else {
+			index.push(Indic::new(IndicKind::Checksums, 0, 0, 0, 0));
+			Some(index.len() - 1)
+		};
+
+		for ((item, (path, attr)), link) in self.items.into_iter().zip(numbers).zip(links) {
+			let data = match item.data {
+				Some(Data::Hardlink(_)) => link.map(Data::Raw),
+				Some(data) if dictionary.is_some() => Some(data.read_ahead(threshold).await?),
+				data => data,
+			};
+
+			let position = index.len();
+			let length = match data {
+				Some(Data::Small(data)) => {
+					record(position, Digests::of(&algorithms, &data));
+					write_entry(output, self.compression, dictionary, &data[..]).await?
+				}
+				Some(Data::Unread(data)) | Some(Data::Large(data)) => {
+					let mut data = Hashing::new(data, &algorithms);
+					let length = write_entry(output, self.compression, None, &mut data).await?;
+					record(position, data.finish());
+					length
+				}
+				Some(Data::OnDisk(path)) => {
+					let mut data = Hashing::new(AllowStdIo::new(File::open(path)?), &algorithms);
+					let length = write_entry(output, self.compression, None, &mut data).await?;
+					record(position, data.finish());
+					length
+				}
+				Some(Data::Raw(data)) => {
+					record(position, Digests::of(&algorithms, &data));
+					write_entry(output, Compression::None, None, &data[..]).await?
+				}
+				Some(Data::Hardlink(_)) => unreachable!("hardlinks are resolved above"),
+				None => 0,
+			};
+
+			index.push(Indic::new(item.kind, path, attr, offset, length));
+			offset += length;
+		}
+
+		if let Some(position) = checksums_at {
+			let mut checksums_entry = Vec::with_capacity(4 + checksums.len() * CHECKSUM_SIZE);
+			checksums_entry.extend(&(checksums.len() as u32).to_le_bytes());
+			for checksum in &checksums {
+				checksums_entry.extend(checksum.to_bytes()?);
+			}
+
+			// digests don't compress, and this way readers can seek through the records
+			let length =
+				write_metadata(output, Compression::None, &checksums_entry, &mut metadata).await?;
+			index[position] = Indic::new(IndicKind::Checksums, 0, 0, offset, length);
+			offset += length;
+		}
+
+		// covers the header and index, so its own indic has to be known before the digest is
+		let raw_header = EntryHeader::new(Encoding::Raw, Vec::new()).to_bytes()?;
+		let integrity_length = (raw_header.len() + INTEGRITY_SIZE) as u64;
+		if self.integrity.is_some() {
+			index[0] = Indic::new(IndicKind::Integrity, 0, 0, offset, integrity_length);
+			offset += integrity_length;
+		}
+
+		let header = ContainerHeader {
+			integrity: self.integrity.is_some(),
+			mode: self.mode,
+			index_bytes,
+			entries_bytes: offset,
+		};
+
+		let mut head = header.to_bytes()?;
+		for indic in index {
+			head.extend(indic.to_bytes()?);
+		}
+
+		if let Some(algorithm) = self.integrity {
+			let mut digests = Digests::new(Some(algorithm));
+			digests.update(&head);
+			for stored in &metadata {
+				digests.update(stored);
+			}
+			let (algorithm, digest) = digests.finish()[0];
+
+			let entry = Integrity { algorithm, digest }.to_bytes()?;
+			let length = write_entry(output, Compression::None, None, &entry[..]).await?;
+			debug_assert_eq!(length, integrity_length);
+		}
+
+		let end = output.seek(SeekFrom::Current(0)).await?;
+		output.seek(SeekFrom::Start(start)).await?;
+		output.write_all(&head).await?;
+		output.seek(SeekFrom::Start(end)).await?;
+		output.flush().await?;
+
+		Ok(header)
+	}
+}
+
+/// Assigns 1-indexed numbers to paths or attributes, deduplicating as it goes.
+struct Numbered<T> {
+	numbers: BTreeMap<T, u32>,
+	items: Vec<T>,
+}
+
+impl<T> Default for Numbered<T> {
+	fn default() -> Self {
+		Self {
+			numbers: BTreeMap::new(),
+			items: Vec::new(),
+		}
+	}
+}
+
+impl<T: Clone + Ord> Numbered<T> {
+	fn number(&mut self, item: &T) -> u32 {
+		if let Some(n) = self.numbers.get(item) {
+			return *n;
+		}
+
+		self.items.push(item.clone());
+		let n = self.items.len() as u32;
+		self.numbers.insert(item.clone(), n);
+		n
+	}
+
+	fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	/// Serialise as a count, a lookup table, then the items themselves.
+	///
+	/// Lookup offsets are relative to the start of the items, just past the table.
+	fn entry(
+		&self,
+		serialise: impl Fn(&T) -> Result<Vec<u8>, DekuError>,
+	) -> Result<Vec<u8>, TomoError> {
+		let mut lookups = Vec::with_capacity(4 + self.items.len() * LOOKUP_SIZE);
+		lookups.extend(&(self.items.len() as u32).to_le_bytes());
+
+		let mut items = Vec::new();
+		for (n, item) in self.items.iter().enumerate() {
+			let lookup = Lookup {
+				index: n as u32 + 1,
+				offset: items.len() as u64,
+			};
+			lookups.extend(lookup.to_bytes()?);
+			items.extend(serialise(item)?);
+		}
+
+		lookups.extend(items);
+		Ok(lookups)
+	}
+}
+
+/// A node of the ReversePaths tree, with the positions of the indics at its path.
+#[derive(Default)]
+struct ReverseNode<'p> {
+	indics: Vec<u64>,
+	children: BTreeMap<&'p PathSeg, ReverseNode<'p>>,
+}
+
+impl<'p> ReverseNode<'p> {
+	fn insert(&mut self, segments: &'p [PathSeg], indic: u64) {
+		match segments.split_first() {
+			None => self.indics.push(indic),
+			Some((seg, rest)) => self.children.entry(seg).or_default().insert(rest, indic),
+		}
+	}
+
+	/// Serialise depth-first, children coming after their parent's table.
+	fn serialise(&self, out: &mut Vec<u8>) -> Result<(), TomoError> {
+		out.extend(&(self.indics.len() as u32).to_le_bytes());
+		for indic in &self.indics {
+			out.extend(&indic.to_le_bytes());
+		}
+
+		out.extend(&(self.children.len() as u32).to_le_bytes());
+		let table = out.len();
+		out.resize(table + self.children.len() * 8, 0);
+
+		for (i, (seg, child)) in self.children.iter().enumerate() {
+			let offset = (out.len() as u64).to_le_bytes();
+			out[(table + i * 8)..(table + i * 8 + 8)].copy_from_slice(&offset);
+
+			let seg = seg.to_item()?;
+			out.extend(&(seg.len() as u32).to_le_bytes());
+			out.extend(seg);
+			child.serialise(out)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Read ahead through every file and train a dictionary from the small ones.
+///
+/// Returns `None` if training fails, which zstd does when given too few or too small samples.
+async fn train(
+	items: &mut [Item<'_>],
+	threshold: u64,
+	max_size: usize,
+) -> Result<Option<Vec<u8>>, TomoError> {
+	for item in items.iter_mut() {
+		if let Some(data) = item.data.take() {
+			item.data = Some(data.read_ahead(threshold).await?);
+		}
+	}
+
+	let samples = items
+		.iter()
+		.filter_map(|item| match &item.data {
+			Some(Data::Small(data)) if !data.is_empty() => Some(&data[..]),
+			_ => None,
+		})
+		.collect::<Vec<_>>();
+
+	if samples.is_empty() {
+		return Ok(None);
+	}
+
+	Ok(zstd::dict::from_samples(&samples, max_size).ok())
+}
+
+/// Write a metadata entry, keeping what was written for the Integrity digest, and return its
+/// length.
+async fn write_metadata<W: AsyncWrite + Unpin>(
+	output: &mut W,
+	compression: Compression,
+	data: &[u8],
+	metadata: &mut Vec<Vec<u8>>,
+) -> Result<u64, TomoError> {
+	let mut stored = Cursor::new(Vec::new());
+	let length = write_entry(&mut stored, compression, None, data).await?;
+	output.write_all(stored.get_ref()).await?;
+	metadata.push(stored.into_inner());
+	Ok(length)
+}
+
+/// Write an entry (header and encoded data) and return its length.
+///
+/// The dictionary, if any, is given with the number of the indic it's stored at.
+async fn write_entry<W: AsyncWrite + Unpin, R: AsyncRead + Unpin>(
+	output: &mut W,
+	compression: Compression,
+	dictionary: Option<(u64, &[u8])>,
+	data: R,
+) -> Result<u64, TomoError> {
+	let header = match (compression, dictionary) {
+		(Compression::None, _) => EntryHeader::new(Encoding::Raw, Vec::new()),
+		(Compression::Zstd { .. }, None) => EntryHeader::new(Encoding::Zstd, Vec::new()),
+		(Compression::Zstd { .. }, Some((dictionary, _))) => {
+			EntryHeader::new(Encoding::Zstd, ZstdParams { dictionary }.to_bytes()?)
+		}
+	}
+	.to_bytes()?;
+	output.write_all(&header).await?;
+
+	let written = match (compression, dictionary) {
+		(Compression::None, _) => copy(data, output).await?,
+		(Compression::Zstd { level }, None) => {
+			let encoder = ZstdEncoder::with_quality(BufReader::new(data), Level::Precise(level));
+			copy(encoder, output).await?
+		}
+		(Compression::Zstd { level }, Some((_, dict))) => {
+			let encoder =
+				ZstdEncoder::with_dict(BufReader::new(data), Level::Precise(level), dict)?;
+			copy(encoder, output).await?
+		}
+	};
+
+	Ok(header.len() as u64 + written)
+}
@@ -0,0 +1,259 @@
+use super::TomoWriter;
+use crate::{
+	parsers::{Attributes, Path, PathSeg},
+	Glob, TomoError,
+};
+use std::{
+	collections::HashMap,
+	ffi::OsStr,
+	fs, io,
+	path::{Path as StdPath, PathBuf},
+};
+
+/// What to pack with [`TomoWriter::add_dir_tree`].
+///
+/// By default, everything is packed, symlinks are stored as symlinks, and other filesystems
+/// mounted within the tree are not descended into.
+#[derive(Clone, Debug, Default)]
+pub struct PackOptions {
+	/// If not empty, only files (and other non-directories) matching one of these are packed.
+	///
+	/// Directories are packed if they match or if anything under them is packed.
+	pub include: Vec<Glob>,
+
+	/// Paths matching any of these are not packed, nor is anything under them.
+	pub exclude: Vec<Glob>,
+
+	/// Pack what symlinks point to instead of the symlinks themselves.
+	///
+	/// Symlinks that point nowhere are still stored as symlinks, and directories already being
+	/// walked are not walked again, so loops are broken.
+	pub follow_symlinks: bool,
+
+	/// Descend into other filesystems mounted within the tree. Mount points are always packed.
+	pub cross_filesystems: bool,
+}
+
+impl PackOptions {
+	fn excluded(&self, path: &Path) -> bool {
+		self.exclude.iter().any(|glob| glob.matches(path))
+	}
+
+	fn included(&self, path: &Path) -> bool {
+		self.include.is_empty() || self.include.iter().any(|glob| glob.matches(path))
+	}
+}
+
+impl<'w> TomoWriter<'w> {
+	/// Queue everything in a directory, walking it recursively.
+	///
+	/// Paths are relative to the directory, which isn't itself queued, and are matched against
+	/// the [`PackOptions`] globs as such. Attributes, including xattrs and ACLs, are captured
+	/// now, but files are only opened during [`TomoWriter::finish`] (see
+	/// [`TomoWriter::add_file_from_disk`]). Files with multiple links to them are queued once,
+	/// and then as hardlinks to that first path. Entries are queued in bytewise order of their
+	/// names, whatever order the filesystem lists them in.
+	///
+	/// Fails with [`TomoError::NonUnicodePath`] on platforms where names aren't bytes, if a name
+	/// isn't valid UTF-8.
+	pub fn add_dir_tree(&mut self, dir: &StdPath, options: &PackOptions) -> Result<(), TomoError> {
+		let root = fs::metadata(dir)?;
+		let mut walk = Walk {
+			options,
+			device: device(&root),
+			walking: inode(&root).into_iter().collect(),
+			links: HashMap::new(),
+		};
+
+		walk.dir(self, dir, &[])?;
+		Ok(())
+	}
+}
+
+struct Walk<'o> {
+	options: &'o PackOptions,
+	device: u64,
+	walking: Vec<(u64, u64)>,
+	links: HashMap<(u64, u64), Path>,
+}
+
+impl Walk<'_> {
+	/// Queue the contents of a directory. Returns whether anything was queued.
+	fn dir(
+		&mut self,
+		writer: &mut TomoWriter<'_>,
+		dir: &StdPath,
+		parent: &[PathSeg],
+	) -> Result<bool, TomoError> {
+		let mut entries = fs::read_dir(dir)?
+			.map(|entry| entry.map(|entry| entry.file_name()))
+			.collect::<Result<Vec<_>, _>>()?;
+		entries.sort_by_key(|name| name_bytes(name));
+
+		let mut queued = false;
+		for name in entries {
+			let mut segments = parent.to_vec();
+			segments
+				.push(segment(&name).ok_or_else(|| TomoError::NonUnicodePath(dir.join(&name)))?);
+			let path = Path::new(segments.clone());
+			if self.options.excluded(&path) {
+				continue;
+			}
+
+			let on_disk = dir.join(&name);
+			let metadata = self.metadata(&on_disk)?;
+			let mut attrs = Attributes::from_metadata(&metadata);
+			// from the same file as the metadata
+			if self.options.follow_symlinks && !metadata.file_type().is_symlink() {
+				attrs.read_xattrs_deref(&on_disk)?;
+			} else {
+				attrs.read_xattrs(&on_disk)?;
+			}
+
+			if metadata.is_dir() {
+				let id = inode(&metadata);
+				let position = writer.items.len();
+				writer.add_dir(path.clone(), Some(attrs));
+
+				let descend = id.is_none_or(|id| !self.walking.contains(&id))
+					&& (self.options.cross_filesystems || device(&metadata) == self.device);
+				let mut inner = false;
+				if descend {
+					let depth = self.walking.len();
+					self.walking.extend(id);
+					inner = self.dir(writer, &on_disk, &segments)?;
+					self.walking.truncate(depth);
+				}
+
+				if inner || self.options.included(&path) {
+					queued = true;
+				} else {
+					writer.items.truncate(position);
+				}
+			} else if self.options.included(&path) {
+				queued |= self.other(writer, path, attrs, &metadata, on_disk)?;
+			}
+		}
+
+		Ok(queued)
+	}
+
+	/// Queue anything that isn't a directory. Returns false if it's of a kind that can't be.
+	fn other(
+		&mut self,
+		writer: &mut TomoWriter<'_>,
+		path: Path,
+		attrs: Attributes,
+		metadata: &fs::Metadata,
+		on_disk: PathBuf,
+	) -> Result<bool, TomoError> {
+		let file_type = metadata.file_type();
+		if file_type.is_file() {
+			if let Some(id) = inode(metadata).filter(|_| links(metadata) > 1) {
+				if let Some(target) = self.links.get(&id) {
+					writer.add_hardlink(path, Some(attrs), target.clone());
+					return Ok(true);
+				}
+				self.links.insert(id, path.clone());
+			}
+
+			writer.add_file_from_disk(path, Some(attrs), on_disk);
+			return Ok(true);
+		}
+
+		if file_type.is_symlink() {
+			let target = fs::read_link(&on_disk)?;
+			let target =
+				name_bytes(target.as_os_str()).ok_or(TomoError::NonUnicodePath(target.clone()))?;
+			writer.add_symlink(path, Some(attrs), target);
+			return Ok(true);
+		}
+
+		#[cfg(unix)]
+		{
+			use crate::parsers::Device;
+			use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+			let device = || Device {
+				major: libc::major(metadata.rdev() as _) as u32,
+				minor: libc::minor(metadata.rdev() as _) as u32,
+			};
+
+			if file_type.is_char_device() {
+				writer.add_char_device(path, Some(attrs), device());
+			} else if file_type.is_block_device() {
+				writer.add_block_device(path, Some(attrs), device());
+			} else if file_type.is_fifo() {
+				writer.add_fifo(path, Some(attrs));
+			} else if file_type.is_socket() {
+				writer.add_socket(path, Some(attrs));
+			} else {
+				return Ok(false);
+			}
+			Ok(true)
+		}
+
+		#[cfg(not(unix))]
+		Ok(false)
+	}
+
+	fn metadata(&self, on_disk: &StdPath) -> io::Result<fs::Metadata> {
+		if self.options.follow_symlinks {
+			match fs::metadata(on_disk) {
+				Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+				other => return other,
+			}
+		}
+
+		fs::symlink_metadata(on_disk)
+	}
+}
+
+/// A name as a path segment. Returns `None` if it can't be represented.
+fn segment(name: &OsStr) -> Option<PathSeg> {
+	name_bytes(name).map(PathSeg::from_name)
+}
+
+fn name_bytes(name: &OsStr) -> Option<Vec<u8>> {
+	#[cfg(unix)]
+	{
+		use std::os::unix::ffi::OsStrExt;
+		Some(name.as_bytes().to_vec())
+	}
+
+	#[cfg(not(unix))]
+	{
+		name.to_str().map(|name| name.as_bytes().to_vec())
+	}
+}
+
+#[cfg(unix)]
+fn device(metadata: &fs::Metadata) -> u64 {
+	std::os::unix::fs::MetadataExt::dev(metadata)
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+	use std::os::unix::fs::MetadataExt;
+	Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(unix)]
+fn links(metadata: &fs::Metadata) -> u64 {
+	std::os::unix::fs::MetadataExt::nlink(metadata)
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &fs::Metadata) -> u64 {
+	0
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+	None
+}
+
+#[cfg(not(unix))]
+fn links(_metadata: &fs::Metadata) -> u64 {
+	1
+}
@@ -0,0 +1,182 @@
+use crate::parsers::{AclEntry, Attributes};
+use deku::{ctx::Endian, prelude::*};
+use std::{io, path::Path};
+
+/// Where Linux stores POSIX ACLs.
+const ACCESS_ACL: &str = "system.posix_acl_access";
+const DEFAULT_ACL: &str = "system.posix_acl_default";
+
+/// Version of the Linux ACL xattr layout.
+const ACL_VERSION: u32 = 2;
+
+impl Attributes {
+	/// Read the extended attributes and POSIX ACLs of a file into these attributes.
+	///
+	/// Symlinks are not followed. Filesystems (and platforms) without xattr support are treated
+	/// as having none, rather than erroring.
+	pub fn read_xattrs(&mut self, path: &Path) -> io::Result<()> {
+		self.read_xattrs_of(path, false)
+	}
+
+	/// Same as [`Attributes::read_xattrs`], but reads those of what symlinks point to.
+	pub fn read_xattrs_deref(&mut self, path: &Path) -> io::Result<()> {
+		self.read_xattrs_of(path, true)
+	}
+
+	fn read_xattrs_of(&mut self, path: &Path, deref: bool) -> io::Result<()> {
+		#[cfg(unix)]
+		{
+			let listed = if deref {
+				xattr::list_deref(path)
+			} else {
+				xattr::list(path)
+			};
+			let names = match listed {
+				Ok(names) => names,
+				Err(err) if skippable(&err) => return Ok(()),
+				Err(err) => return Err(err),
+			};
+
+			for name in names {
+				let value = if deref {
+					xattr::get_deref(path, &name)
+				} else {
+					xattr::get(path, &name)
+				};
+				let value = match value {
+					Ok(Some(value)) => value,
+					Ok(None) => continue,
+					Err(err) if skippable(&err) => continue,
+					Err(err) => return Err(err),
+				};
+
+				if name == ACCESS_ACL {
+					self.access_acl = Some(parse_acl(&value)?);
+				} else if name == DEFAULT_ACL {
+					self.default_acl = Some(parse_acl(&value)?);
+				} else {
+					use std::os::unix::ffi::OsStrExt;
+					self.xattrs.insert(name.as_bytes().to_vec(), value);
+				}
+			}
+		}
+
+		#[cfg(not(unix))]
+		let _ = (path, deref);
+
+		Ok(())
+	}
+
+	/// Set the extended attributes and POSIX ACLs from these attributes onto a file.
+	///
+	/// Symlinks are not followed. Attributes that can't be set for lack of permission (e.g.
+	/// `trusted.*` when not privileged) or of support (from the filesystem or platform) are
+	/// skipped; other errors are returned.
+	pub fn restore_xattrs(&self, path: &Path) -> io::Result<()> {
+		#[cfg(unix)]
+		{
+			use std::ffi::OsStr;
+			use std::os::unix::ffi::OsStrExt;
+
+			let mut all = self
+				.xattrs
+				.iter()
+				.map(|(name, value)| (OsStr::from_bytes(name), value.clone()))
+				.collect::<Vec<_>>();
+			if let Some(acl) = &self.access_acl {
+				all.push((OsStr::new(ACCESS_ACL), write_acl(acl)?));
+			}
+			if let Some(acl) = &self.default_acl {
+				all.push((OsStr::new(DEFAULT_ACL), write_acl(acl)?));
+			}
+
+			for (name, value) in all {
+				match xattr::set(path, name, &value) {
+					Err(err) if skippable(&err) => {}
+					other => other?,
+				}
+			}
+		}
+
+		#[cfg(not(unix))]
+		let _ = path;
+
+		Ok(())
+	}
+}
+
+fn skippable(err: &io::Error) -> bool {
+	matches!(
+		err.kind(),
+		io::ErrorKind::PermissionDenied | io::ErrorKind::Unsupported
+	)
+}
+
+fn parse_acl(value: &[u8]) -> io::Result<Vec<AclEntry>> {
+	let invalid = |err: DekuError| io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+
+	let (mut rest, version) = u32::read(value.view_bits(), Endian::Little).map_err(invalid)?;
+	if version != ACL_VERSION {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("unknown ACL xattr version {}", version),
+		));
+	}
+
+	let mut entries = Vec::new();
+	while !rest.is_empty() {
+		let (r, entry) = AclEntry::read(rest, Endian::Little).map_err(invalid)?;
+		rest = r;
+		entries.push(entry);
+	}
+
+	Ok(entries)
+}
+
+fn write_acl(entries: &[AclEntry]) -> io::Result<Vec<u8>> {
+	let invalid = |err: DekuError| io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+
+	let mut bits = BitVec::<Msb0, u8>::new();
+	ACL_VERSION
+		.write(&mut bits, Endian::Little)
+		.map_err(invalid)?;
+	for entry in entries {
+		entry.write(&mut bits, Endian::Little).map_err(invalid)?;
+	}
+	Ok(bits.into_vec())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::parsers::AclTag;
+
+	#[test]
+	fn acl_xattr() {
+		let mut value = Vec::new();
+		value.extend(&2_u32.to_le_bytes());
+		value.extend(&[0x01, 0x00, 0x06, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]); // user::rw-
+		value.extend(&[0x02, 0x00, 0x04, 0x00, 0xE8, 0x03, 0x00, 0x00]); // user:1000:r--
+
+		let entries = parse_acl(&value).unwrap();
+		assert_eq!(
+			entries,
+			vec![
+				AclEntry {
+					tag: AclTag::UserObj,
+					perm: 6,
+					id: u32::MAX,
+				},
+				AclEntry {
+					tag: AclTag::User,
+					perm: 4,
+					id: 1000,
+				},
+			]
+		);
+		assert_eq!(write_acl(&entries).unwrap(), value);
+
+		value[0] = 1;
+		assert!(parse_acl(&value).is_err());
+	}
+}
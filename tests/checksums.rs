@@ -0,0 +1,203 @@
+use common::{archive, entry_data, sample};
+use deku::DekuContainerRead;
+use eyre::Result;
+use futures::io::{AsyncReadExt, Cursor};
+use sha2::{Digest, Sha256};
+use tomo::parsers::{Checksum, ChecksumAlgorithm, IndicKind, Path, CHECKSUM_SIZE};
+use tomo::prelude::*;
+use tomo::{Compression, Corruption, TomoError};
+
+mod common;
+
+/// Flip a bit in the first occurrence of some bytes.
+fn corrupt(data: &mut [u8], needle: &[u8]) {
+	let at = data
+		.windows(needle.len())
+		.position(|window| window == needle)
+		.expect("needle is in the data");
+	data[at] ^= 0x01;
+}
+
+#[async_std::test]
+async fn written() -> Result<()> {
+	let mut writer = sample();
+	writer.set_checksums(&[ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Blake3]);
+	let mut reader = Cursor::new(archive(writer).await?);
+	let mut tomo = Tomo::default();
+	let (ss, _) = tomo.load_one(Seekable::new(&mut reader)).await?;
+
+	let mut entries = ss.entries(0).unwrap();
+	let mut records = Vec::new();
+	while let Some(entry) = entries.next().await {
+		let mut entry = entry?;
+		if entry.indic.kind == IndicKind::Checksums {
+			let mut data = Vec::new();
+			entry.reader.read_to_end(&mut data).await?;
+			let count = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+			assert_eq!(data.len(), 4 + count * CHECKSUM_SIZE);
+			for record in data[4..].chunks(CHECKSUM_SIZE) {
+				records.push(Checksum::from_bytes((record, 0))?.1);
+			}
+		}
+	}
+
+	// Paths, ReversePaths, two files and a symlink, with both algorithms each
+	assert_eq!(records.len(), 10);
+	let mut sorted = records.clone();
+	sorted.sort();
+	assert_eq!(records, sorted);
+
+	let resolved = tomo.resolve().await?;
+	for item in resolved.iter().filter(|i| i.indic.kind == IndicKind::File) {
+		let mut data = Vec::new();
+		tomo.open_indexed(item)
+			.await?
+			.read_to_end(&mut data)
+			.await?;
+		let find = |algorithm| {
+			records
+				.iter()
+				.find(|r| r.indic == item.position && r.algorithm == algorithm)
+				.unwrap()
+				.digest
+		};
+		assert_eq!(
+			find(ChecksumAlgorithm::Blake3),
+			*blake3::hash(&data).as_bytes()
+		);
+		assert_eq!(
+			find(ChecksumAlgorithm::Sha256),
+			<[u8; 32]>::from(Sha256::digest(&data))
+		);
+	}
+
+	let verification = tomo.verify().await?;
+	assert!(verification.is_ok());
+	assert_eq!(verification.checked, 5);
+	assert_eq!(verification.unchecked_containers, 0);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_entry() -> Result<()> {
+	let mut data = archive(sample()).await?;
+	corrupt(&mut data, b"second file contents");
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let verification = tomo.verify().await?;
+	assert_eq!(verification.checked, 5);
+	assert_eq!(verification.corrupt.len(), 1);
+	let corrupt = &verification.corrupt[0];
+	assert_eq!(corrupt.path, Some(Path::from_names(&["second"])));
+	assert_eq!(corrupt.indic.kind, IndicKind::File);
+	assert!(matches!(
+		corrupt.problem,
+		Corruption::Mismatch(ChecksumAlgorithm::Blake3)
+	));
+
+	let mut content = Vec::new();
+	let err = tomo
+		.open(&Path::from_names(&["second"]))
+		.await?
+		.unwrap()
+		.read_to_end(&mut content)
+		.await
+		.unwrap_err();
+	assert!(matches!(
+		err.into_inner()
+			.unwrap()
+			.downcast::<TomoError>()
+			.map(|e| *e),
+		Ok(TomoError::ChecksumMismatch {
+			algorithm: ChecksumAlgorithm::Blake3,
+			..
+		})
+	));
+
+	// other entries still read fine
+	content.clear();
+	tomo.open(&Path::from_names(&["dir", "first"]))
+		.await?
+		.unwrap()
+		.read_to_end(&mut content)
+		.await?;
+	assert_eq!(content, b"first file contents");
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_compressed() -> Result<()> {
+	let contents = "compressible ".repeat(100);
+	let mut writer = TomoWriter::default();
+	writer.set_compression(Compression::Zstd { level: 3 });
+	writer.add_file(Path::from_names(&["a"]), None, contents.as_bytes());
+	writer.add_file(Path::from_names(&["b"]), None, contents.as_bytes());
+
+	let mut data = archive(writer).await?;
+	let magic = [0x28, 0xB5, 0x2F, 0xFD];
+	let frame = data
+		.windows(4)
+		.rposition(|window| window == magic)
+		.expect("a zstd frame");
+	// past the frame header, into the compressed block
+	data[frame + 10] ^= 0xFF;
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let verification = tomo.verify().await?;
+	assert_eq!(verification.corrupt.len(), 1);
+	assert_eq!(verification.corrupt[0].path, Some(Path::from_names(&["b"])));
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn without_checksums() -> Result<()> {
+	let mut writer = sample();
+	writer.set_checksums(&[]);
+	let mut data = archive(writer).await?;
+	corrupt(&mut data, b"second file contents");
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let verification = tomo.verify().await?;
+	assert!(verification.is_ok());
+	assert_eq!(verification.checked, 0);
+	assert_eq!(verification.unchecked_containers, 1);
+
+	let mut content = Vec::new();
+	tomo.open(&Path::from_names(&["second"]))
+		.await?
+		.unwrap()
+		.read_to_end(&mut content)
+		.await?;
+	assert_ne!(content, b"second file contents");
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn bad_count() -> Result<()> {
+	let mut writer = sample();
+	writer.set_integrity(None);
+	let mut data = archive(writer).await?;
+	let at = entry_data(&data, IndicKind::Checksums);
+	data[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	assert!(matches!(
+		tomo.verify().await,
+		Err(TomoError::UnexpectedEof { .. })
+	));
+
+	Ok(())
+}
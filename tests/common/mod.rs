@@ -0,0 +1,72 @@
+//! Fixtures shared by some of the integration tests.
+
+// each test crate only uses some of these
+#![allow(dead_code)]
+
+use deku::DekuContainerRead;
+use eyre::Result;
+use futures::io::Cursor;
+use tomo::parsers::{
+	Attributes, ContainerHeader, Indic, IndicKind, Path, CONTAINER_HEADER_SIZE, INDIC_SIZE,
+};
+use tomo::prelude::*;
+
+pub async fn archive(writer: TomoWriter<'_>) -> Result<Vec<u8>> {
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+	Ok(output.into_inner())
+}
+
+/// A directory, two files, and a symlink.
+pub fn sample() -> TomoWriter<'static> {
+	let mut writer = TomoWriter::default();
+	writer.add_dir(Path::from_names(&["dir"]), None);
+	writer.add_file(
+		Path::from_names(&["dir", "first"]),
+		None,
+		&b"first file contents"[..],
+	);
+	writer.add_file(
+		Path::from_names(&["second"]),
+		None,
+		&b"second file contents"[..],
+	);
+	writer.add_symlink(Path::from_names(&["link"]), None, b"second".to_vec());
+	writer
+}
+
+/// A directory, and top-level files with the given names and contents.
+pub async fn container(files: &[(&str, &str)]) -> Result<Vec<u8>> {
+	let mut writer = TomoWriter::default();
+	writer.add_dir(Path::from_names(&["dir"]), None);
+	for (name, content) in files {
+		writer.add_file(Path::from_names(&[name]), None, content.as_bytes());
+	}
+	archive(writer).await
+}
+
+/// A file at each path, all with the same attributes and contents.
+pub async fn files(paths: &[&[&str]]) -> Result<Vec<u8>> {
+	let mut writer = TomoWriter::default();
+	for segs in paths {
+		writer.add_file(
+			Path::from_names(segs),
+			Some(Attributes::new(0o644)),
+			&b"content"[..],
+		);
+	}
+	archive(writer).await
+}
+
+/// Where the data of the first entry of a kind starts in a single raw container, past its
+/// (raw) entry header.
+pub fn entry_data(data: &[u8], kind: IndicKind) -> usize {
+	let (_, header) = ContainerHeader::from_bytes((data, 0)).unwrap();
+	let entries = CONTAINER_HEADER_SIZE + header.index_bytes as usize;
+	data[CONTAINER_HEADER_SIZE..entries]
+		.chunks(INDIC_SIZE as usize)
+		.map(|bytes| Indic::from_bytes((bytes, 0)).unwrap().1)
+		.find(|indic| indic.kind == kind)
+		.map(|indic| entries + indic.offset as usize + 2)
+		.expect("an entry of that kind")
+}
@@ -0,0 +1,74 @@
+use eyre::Result;
+use futures::io::{AsyncReadExt, Cursor};
+use tomo::parsers::{Attributes, Encoding, IndicKind, Path};
+use tomo::prelude::*;
+
+async fn archive() -> Result<Vec<u8>> {
+	let mut writer = TomoWriter::default();
+	writer.add_file(
+		Path::from_names(&["a"]),
+		Some(Attributes::new(0o644)),
+		&b"first file"[..],
+	);
+	writer.add_dir(Path::from_names(&["dir"]), None);
+	writer.add_file(Path::from_names(&["dir", "b"]), None, &b"second file"[..]);
+
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+	Ok(output.into_inner())
+}
+
+#[async_std::test]
+async fn every_entry() -> Result<()> {
+	let mut reader = Cursor::new(archive().await?);
+	let mut tomo = Tomo::default();
+	let (ss, _) = tomo.load_one(Seekable::new(&mut reader)).await?;
+
+	let mut entries = ss.entries(0).unwrap();
+	let mut seen = Vec::new();
+	while let Some(entry) = entries.next().await {
+		let mut entry = entry?;
+		assert_eq!(entry.reader.header().encoding(), Encoding::Raw);
+
+		let mut data = Vec::new();
+		entry.reader.read_to_end(&mut data).await?;
+		seen.push((entry.indic.kind, data));
+	}
+
+	assert_eq!(seen.len(), 7);
+	assert_eq!(seen[0].0, IndicKind::Integrity);
+	assert_eq!(seen[1].0, IndicKind::Paths);
+	assert_eq!(seen[2].0, IndicKind::Attributes);
+	assert_eq!(seen[3].0, IndicKind::ReversePaths);
+	assert_eq!(seen[4].0, IndicKind::Checksums);
+	assert_eq!(seen[5], (IndicKind::File, b"first file".to_vec()));
+	assert_eq!(seen[6], (IndicKind::File, b"second file".to_vec()));
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn partial_reads() -> Result<()> {
+	let mut reader = Cursor::new(archive().await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let ss = tomo.source_mut(0).unwrap();
+	let mut entries = ss.entries(0).unwrap();
+	let mut starts = Vec::new();
+	while let Some(entry) = entries.next().await {
+		let mut entry = entry?;
+		if entry.indic.kind != IndicKind::File {
+			continue;
+		}
+
+		let mut start = [0; 6];
+		entry.reader.read_exact(&mut start).await?;
+		starts.push(start);
+	}
+
+	assert_eq!(starts, vec![*b"first ", *b"second"]);
+	assert!(ss.entries(1).is_none());
+
+	Ok(())
+}
@@ -0,0 +1,220 @@
+#![cfg(unix)]
+
+use common::archive;
+use eyre::Result;
+use futures::io::Cursor;
+use std::{
+	env, fs,
+	os::unix::fs::{symlink, FileTypeExt, MetadataExt, PermissionsExt},
+	path::PathBuf,
+	process,
+	time::{Duration, UNIX_EPOCH},
+};
+use tomo::parsers::{Attributes, Path, PathSeg};
+use tomo::prelude::*;
+use tomo::TomoError;
+
+mod common;
+
+fn temp_dir(name: &str) -> PathBuf {
+	let dir = env::temp_dir().join(format!("tomo-extract-{}-{}", name, process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	dir
+}
+
+#[async_std::test]
+async fn tree() -> Result<()> {
+	let mut attrs = Attributes::new(0o640);
+	attrs.mtime = Some((UNIX_EPOCH + Duration::from_secs(1_600_000_000)).into());
+
+	let mut writer = TomoWriter::default();
+	writer.add_dir(Path::from_names(&["dir"]), Some(Attributes::new(0o750)));
+	writer.add_file(
+		Path::from_names(&["dir", "file"]),
+		Some(attrs),
+		&b"content"[..],
+	);
+	writer.add_file(
+		Path::from_names(&["deep", "er", "file"]),
+		None,
+		&b"deeper"[..],
+	);
+	writer.add_hardlink(
+		Path::from_names(&["link"]),
+		None,
+		Path::from_names(&["dir", "file"]),
+	);
+	writer.add_symlink(Path::from_names(&["sym"]), None, b"dir/file".to_vec());
+	writer.add_fifo(Path::from_names(&["pipe"]), None);
+
+	let mut reader = Cursor::new(archive(writer).await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let dir = temp_dir("tree");
+	tomo.extract_to(&dir).await?;
+
+	assert_eq!(fs::read(dir.join("dir/file"))?, b"content");
+	assert_eq!(fs::read(dir.join("deep/er/file"))?, b"deeper");
+	assert_eq!(fs::read(dir.join("sym"))?, b"content");
+	assert_eq!(fs::read_link(dir.join("sym"))?, PathBuf::from("dir/file"));
+
+	let file = fs::metadata(dir.join("dir/file"))?;
+	assert_eq!(file.permissions().mode() & 0o7777, 0o640);
+	assert_eq!(file.mtime(), 1_600_000_000);
+	assert_eq!(fs::metadata(dir.join("link"))?.ino(), file.ino());
+	assert_eq!(
+		fs::metadata(dir.join("dir"))?.permissions().mode() & 0o7777,
+		0o750
+	);
+	assert!(fs::symlink_metadata(dir.join("pipe"))?
+		.file_type()
+		.is_fifo());
+
+	fs::remove_dir_all(&dir)?;
+	Ok(())
+}
+
+#[async_std::test]
+async fn times_with_restrictive_modes() -> Result<()> {
+	let attrs = |mode| {
+		let mut attrs = Attributes::new(mode);
+		attrs.mtime = Some((UNIX_EPOCH + Duration::from_secs(1_500_000_000)).into());
+		attrs
+	};
+
+	let mut writer = TomoWriter::default();
+	writer.add_dir(Path::from_names(&["locked"]), Some(attrs(0o000)));
+	writer.add_file(
+		Path::from_names(&["write-only"]),
+		Some(attrs(0o200)),
+		&b"content"[..],
+	);
+	writer.add_file(
+		Path::from_names(&["none"]),
+		Some(attrs(0o000)),
+		&b"content"[..],
+	);
+
+	let mut reader = Cursor::new(archive(writer).await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let dir = temp_dir("restrictive");
+	tomo.extract_to(&dir).await?;
+
+	for (name, mode) in &[("locked", 0o000), ("write-only", 0o200), ("none", 0o000)] {
+		let meta = fs::metadata(dir.join(name))?;
+		assert_eq!(meta.permissions().mode() & 0o7777, *mode, "{}", name);
+		assert_eq!(meta.mtime(), 1_500_000_000, "{}", name);
+	}
+
+	fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o700))?;
+	fs::remove_dir_all(&dir)?;
+	Ok(())
+}
+
+#[async_std::test]
+async fn unsafe_segments() -> Result<()> {
+	let unsafe_paths = vec![
+		Path::new(vec![PathSeg::Root, PathSeg::Segment(b"etc\0".to_vec())]),
+		Path::from_names(&[".."]),
+		Path::from_names(&["a", ".", "b"]),
+		Path::from_names(&["a/b"]),
+		Path::from_names(&[""]),
+	];
+
+	for unsafe_path in unsafe_paths {
+		let mut writer = TomoWriter::default();
+		writer.add_file(Path::from_names(&["fine"]), None, &b"fine"[..]);
+		writer.add_file(unsafe_path.clone(), None, &b"escape"[..]);
+
+		let mut reader = Cursor::new(archive(writer).await?);
+		let mut tomo = Tomo::default();
+		tomo.load(Seekable::new(&mut reader)).await?;
+
+		let dir = temp_dir("unsafe");
+		match tomo.extract_to(&dir).await {
+			Err(TomoError::UnsafePath(p)) => assert_eq!(p, unsafe_path),
+			other => panic!(
+				"expected unsafe path error for {:?}, got {:?}",
+				unsafe_path, other
+			),
+		}
+		assert!(!dir.exists(), "nothing should be written");
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn through_symlink_in_archive() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	writer.add_symlink(Path::from_names(&["escape"]), None, b"/tmp".to_vec());
+	writer.add_file(Path::from_names(&["escape", "file"]), None, &b"gotcha"[..]);
+
+	let mut reader = Cursor::new(archive(writer).await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let dir = temp_dir("archive-symlink");
+	match tomo.extract_to(&dir).await {
+		Err(TomoError::UnsafePath(p)) => assert_eq!(p, Path::from_names(&["escape", "file"])),
+		other => panic!("expected unsafe path error, got {:?}", other),
+	}
+	assert!(!dir.exists());
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn through_symlink_on_disk() -> Result<()> {
+	let outside = temp_dir("outside");
+	fs::create_dir_all(&outside)?;
+	let dir = temp_dir("disk-symlink");
+	fs::create_dir_all(&dir)?;
+	symlink(&outside, dir.join("escape"))?;
+
+	let mut writer = TomoWriter::default();
+	writer.add_file(Path::from_names(&["escape", "file"]), None, &b"gotcha"[..]);
+
+	let mut reader = Cursor::new(archive(writer).await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	match tomo.extract_to(&dir).await {
+		Err(TomoError::UnsafePath(p)) => assert_eq!(p, Path::from_names(&["escape", "file"])),
+		other => panic!("expected unsafe path error, got {:?}", other),
+	}
+	assert!(!outside.join("file").exists());
+
+	fs::remove_dir_all(&dir)?;
+	fs::remove_dir_all(&outside)?;
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_file() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	writer.add_file(Path::from_names(&["file"]), None, &b"pristine contents"[..]);
+
+	let mut data = archive(writer).await?;
+	let at = data
+		.windows(8)
+		.position(|window| window == b"pristine")
+		.unwrap();
+	data[at] ^= 0x01;
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let dir = temp_dir("corrupt");
+	match tomo.extract_to(&dir).await {
+		Err(TomoError::ChecksumMismatch { .. }) => {}
+		other => panic!("expected checksum mismatch, got {:?}", other),
+	}
+
+	fs::remove_dir_all(&dir)?;
+	Ok(())
+}
@@ -0,0 +1,138 @@
+use common::{archive, sample};
+use deku::DekuContainerRead;
+use eyre::Result;
+use futures::io::{AsyncReadExt, Cursor};
+use tomo::parsers::{ContainerHeader, Path, CONTAINER_HEADER_SIZE, INDIC_SIZE};
+use tomo::prelude::*;
+use tomo::TomoError;
+
+mod common;
+
+async fn load(data: Vec<u8>) -> Result<(), TomoError> {
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	Ok(())
+}
+
+#[async_std::test]
+async fn intact() -> Result<()> {
+	let mut reader = Cursor::new(archive(sample()).await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let mut content = Vec::new();
+	tomo.open(&Path::from_names(&["second"]))
+		.await?
+		.unwrap()
+		.read_to_end(&mut content)
+		.await?;
+	assert_eq!(content, b"second file contents");
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_index() -> Result<()> {
+	let mut data = archive(sample()).await?;
+	let (_, header) = ContainerHeader::from_bytes((&data, 0))?;
+	let last = CONTAINER_HEADER_SIZE as u64 + header.index_bytes - INDIC_SIZE;
+	// the low byte of the last indic's offset
+	data[last as usize + 8] ^= 0x01;
+
+	assert!(matches!(
+		load(data).await,
+		Err(TomoError::CorruptContainer { offset: 0 })
+	));
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_integrity_indic() -> Result<()> {
+	let data = archive(sample()).await?;
+	let first = CONTAINER_HEADER_SIZE;
+
+	// as if it had a path
+	let mut pathed = data.clone();
+	pathed[first + 1] ^= 0x01;
+	// as if it was a Paths indic
+	let mut kind = data.clone();
+	assert_eq!(kind[first], 0xF4);
+	kind[first] ^= 0x04;
+	// the header flag cleared, in the mode byte
+	let mut unflagged = data;
+	unflagged[7] ^= 0x80;
+
+	for data in [pathed, kind, unflagged] {
+		assert!(matches!(
+			load(data).await,
+			Err(TomoError::CorruptContainer { offset: 0 })
+		));
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_paths() -> Result<()> {
+	let mut data = archive(sample()).await?;
+	let at = data
+		.windows(6)
+		.position(|window| window == b"first\0")
+		.expect("the name is in the Paths entry");
+	data[at] ^= 0x01;
+
+	assert!(matches!(
+		load(data).await,
+		Err(TomoError::CorruptContainer { offset: 0 })
+	));
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn truncated() -> Result<()> {
+	let mut data = archive(sample()).await?;
+	data.truncate(data.len() - 1);
+
+	assert!(matches!(
+		load(data).await,
+		Err(TomoError::CorruptContainer { offset: 0 })
+	));
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn second_container() -> Result<()> {
+	let mut data = archive(sample()).await?;
+	let first = data.len();
+	data.extend(archive(sample()).await?);
+	data[first + CONTAINER_HEADER_SIZE + 8] ^= 0x01;
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	let (ss, _) = tomo.load_one(Seekable::new(&mut reader)).await?;
+	assert!(matches!(
+		ss.load_next_container().await,
+		Err(TomoError::CorruptContainer { offset }) if offset == first as u64
+	));
+	assert_eq!(ss.len(), 1);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn without_integrity() -> Result<()> {
+	let mut writer = sample();
+	writer.set_integrity(None);
+	let mut data = archive(writer).await?;
+	let (_, header) = ContainerHeader::from_bytes((&data, 0))?;
+	let last = CONTAINER_HEADER_SIZE as u64 + header.index_bytes - INDIC_SIZE;
+	data[last as usize + 8] ^= 0x01;
+
+	load(data).await?;
+
+	Ok(())
+}
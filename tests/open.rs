@@ -0,0 +1,66 @@
+use common::container;
+use eyre::Result;
+use futures::io::{AsyncReadExt, Cursor};
+use tomo::parsers::{IndicKind, Path};
+use tomo::prelude::*;
+
+mod common;
+
+async fn read(tomo: &mut Tomo<'_>, name: &str) -> Result<Option<String>> {
+	Ok(match tomo.open(&Path::from_names(&[name])).await? {
+		Some(mut reader) => {
+			let mut content = String::new();
+			reader.read_to_string(&mut content).await?;
+			Some(content)
+		}
+		None => None,
+	})
+}
+
+#[async_std::test]
+async fn open_one() -> Result<()> {
+	let data = container(&[("a", "first"), ("b", "second"), ("c", "third")]).await?;
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	assert_eq!(read(&mut tomo, "b").await?.as_deref(), Some("second"));
+	assert_eq!(read(&mut tomo, "a").await?.as_deref(), Some("first"));
+	assert_eq!(read(&mut tomo, "c").await?.as_deref(), Some("third"));
+	assert_eq!(read(&mut tomo, "d").await?, None);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn open_dir() -> Result<()> {
+	let mut reader = Cursor::new(container(&[]).await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	match tomo.open(&Path::from_names(&["dir"])).await {
+		Err(TomoError::NotAFile(IndicKind::Dir)) => {}
+		other => panic!("expected NotAFile, got {:?}", other),
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn open_latest() -> Result<()> {
+	let mut data = container(&[("a", "old a"), ("b", "old b")]).await?;
+	data.extend(container(&[("b", "new b")]).await?);
+	let mut reader = Cursor::new(data);
+
+	let mut other = Cursor::new(container(&[("c", "other c")]).await?);
+
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	tomo.load(Seekable::new(&mut other)).await?;
+
+	assert_eq!(read(&mut tomo, "a").await?.as_deref(), Some("old a"));
+	assert_eq!(read(&mut tomo, "b").await?.as_deref(), Some("new b"));
+	assert_eq!(read(&mut tomo, "c").await?.as_deref(), Some("other c"));
+
+	Ok(())
+}
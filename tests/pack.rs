@@ -0,0 +1,195 @@
+#![cfg(unix)]
+
+use eyre::Result;
+use futures::io::{AsyncReadExt, Cursor};
+use std::{
+	env, fs,
+	os::unix::fs::{symlink, PermissionsExt},
+	path::PathBuf,
+	process,
+};
+use tomo::parsers::{IndicKind, Path};
+use tomo::prelude::*;
+use tomo::{Glob, PackOptions};
+
+fn tree(name: &str) -> Result<PathBuf> {
+	let dir = env::temp_dir().join(format!("tomo-pack-{}-{}", name, process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(dir.join("src/deep"))?;
+	fs::create_dir_all(dir.join("target"))?;
+	fs::write(dir.join("README"), b"read me")?;
+	fs::write(dir.join("src/lib.rs"), b"// lib")?;
+	fs::write(dir.join("src/deep/mod.rs"), b"// mod")?;
+	fs::write(dir.join("src/deep/notes.txt"), b"notes")?;
+	fs::write(dir.join("target/build.o"), b"object")?;
+	fs::hard_link(dir.join("README"), dir.join("README.link"))?;
+	symlink("src/lib.rs", dir.join("lib"))?;
+	fs::set_permissions(dir.join("README"), fs::Permissions::from_mode(0o600))?;
+	Ok(dir)
+}
+
+async fn pack(dir: &std::path::Path, options: &PackOptions) -> Result<Cursor<Vec<u8>>> {
+	let mut writer = TomoWriter::default();
+	writer.add_dir_tree(dir, options)?;
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+	Ok(Cursor::new(output.into_inner()))
+}
+
+async fn kinds(tomo: &mut Tomo<'_>) -> Result<Vec<(Path, IndicKind)>> {
+	Ok(tomo
+		.resolve()
+		.await?
+		.iter()
+		.map(|item| (item.path.clone(), item.indic.kind))
+		.collect())
+}
+
+#[async_std::test]
+async fn everything() -> Result<()> {
+	let dir = tree("everything")?;
+	let mut reader = pack(&dir, &PackOptions::default()).await?;
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	assert_eq!(
+		kinds(&mut tomo).await?,
+		vec![
+			(Path::from_names(&["README"]), IndicKind::File),
+			(Path::from_names(&["README.link"]), IndicKind::Hardlink),
+			(Path::from_names(&["lib"]), IndicKind::Symlink),
+			(Path::from_names(&["src"]), IndicKind::Dir),
+			(Path::from_names(&["target"]), IndicKind::Dir),
+			(Path::from_names(&["src", "deep"]), IndicKind::Dir),
+			(Path::from_names(&["src", "lib.rs"]), IndicKind::File),
+			(Path::from_names(&["target", "build.o"]), IndicKind::File),
+			(
+				Path::from_names(&["src", "deep", "mod.rs"]),
+				IndicKind::File
+			),
+			(
+				Path::from_names(&["src", "deep", "notes.txt"]),
+				IndicKind::File
+			),
+		]
+	);
+
+	let readme = tomo
+		.resolve_path(&Path::from_names(&["README"]))
+		.await?
+		.unwrap();
+	assert_eq!(readme.attrs.unwrap().mode & 0o777, 0o600);
+	assert_eq!(
+		tomo.read_link(&Path::from_names(&["lib"])).await?,
+		Some(b"src/lib.rs".to_vec())
+	);
+
+	let mut data = Vec::new();
+	tomo.open(&Path::from_names(&["README.link"]))
+		.await?
+		.unwrap()
+		.read_to_end(&mut data)
+		.await?;
+	assert_eq!(data, b"read me");
+
+	let out = dir.with_extension("out");
+	tomo.extract_to(&out).await?;
+	assert_eq!(fs::read(out.join("src/deep/mod.rs"))?, b"// mod");
+	assert_eq!(fs::read(out.join("lib"))?, b"// lib");
+
+	fs::remove_dir_all(&dir)?;
+	fs::remove_dir_all(&out)?;
+	Ok(())
+}
+
+#[async_std::test]
+async fn filters() -> Result<()> {
+	let dir = tree("filters")?;
+	let options = PackOptions {
+		include: vec![Glob::new("**/*.rs")?, Glob::new("README")?],
+		exclude: vec![Glob::new("src/deep")?],
+		..PackOptions::default()
+	};
+	let mut reader = pack(&dir, &options).await?;
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	assert_eq!(
+		kinds(&mut tomo).await?,
+		vec![
+			(Path::from_names(&["README"]), IndicKind::File),
+			(Path::from_names(&["src"]), IndicKind::Dir),
+			(Path::from_names(&["src", "lib.rs"]), IndicKind::File),
+		]
+	);
+
+	fs::remove_dir_all(&dir)?;
+	Ok(())
+}
+
+#[async_std::test]
+async fn follow_symlinks() -> Result<()> {
+	let dir = tree("follow")?;
+	symlink(".", dir.join("src/deep/loop"))?;
+	symlink("nowhere", dir.join("dangling"))?;
+	let options = PackOptions {
+		follow_symlinks: true,
+		exclude: vec![Glob::new("target")?, Glob::new("README*")?],
+		..PackOptions::default()
+	};
+	let mut reader = pack(&dir, &options).await?;
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	assert_eq!(
+		kinds(&mut tomo).await?,
+		vec![
+			(Path::from_names(&["dangling"]), IndicKind::Symlink),
+			(Path::from_names(&["lib"]), IndicKind::File),
+			(Path::from_names(&["src"]), IndicKind::Dir),
+			(Path::from_names(&["src", "deep"]), IndicKind::Dir),
+			(Path::from_names(&["src", "lib.rs"]), IndicKind::File),
+			(Path::from_names(&["src", "deep", "loop"]), IndicKind::Dir),
+			(
+				Path::from_names(&["src", "deep", "mod.rs"]),
+				IndicKind::File
+			),
+			(
+				Path::from_names(&["src", "deep", "notes.txt"]),
+				IndicKind::File
+			),
+		]
+	);
+
+	fs::remove_dir_all(&dir)?;
+	Ok(())
+}
+
+#[async_std::test]
+async fn follow_symlinks_xattrs() -> Result<()> {
+	let dir = tree("follow-xattrs")?;
+	match xattr::set(dir.join("src/lib.rs"), "user.tag", b"target") {
+		Err(err) if err.kind() == std::io::ErrorKind::Unsupported => return Ok(()),
+		other => other?,
+	}
+
+	let options = PackOptions {
+		follow_symlinks: true,
+		..PackOptions::default()
+	};
+	let mut reader = pack(&dir, &options).await?;
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let resolved = tomo.resolve().await?;
+	let lib = resolved.get(&Path::from_names(&["lib"])).unwrap();
+	assert_eq!(lib.indic.kind, IndicKind::File);
+	let attrs = lib.attrs.as_ref().unwrap();
+	assert_eq!(
+		attrs.xattrs.get(&b"user.tag"[..]),
+		Some(&b"target".to_vec())
+	);
+
+	fs::remove_dir_all(&dir)?;
+	Ok(())
+}
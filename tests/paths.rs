@@ -0,0 +1,137 @@
+use common::{archive, entry_data, files};
+use eyre::Result;
+use futures::io::Cursor;
+use tomo::parsers::{Attributes, IndicKind, Path};
+use tomo::prelude::*;
+
+mod common;
+
+#[async_std::test]
+async fn all_paths_single() -> Result<()> {
+	let mut reader = Cursor::new(files(&[&["a"], &["b", "c"], &["d"]]).await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let paths = tomo
+		.all_paths()
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()?;
+	assert_eq!(
+		paths,
+		vec![
+			Path::from_names(&["a"]),
+			Path::from_names(&["b", "c"]),
+			Path::from_names(&["d"])
+		]
+	);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn all_paths_catted() -> Result<()> {
+	let mut data = files(&[&["a"]]).await?;
+	data.extend(files(&[]).await?);
+	data.extend(files(&[&["b"], &["c"]]).await?);
+	let mut reader = Cursor::new(data);
+
+	let mut other = Cursor::new(files(&[&["d"]]).await?);
+
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	tomo.load(Seekable::new(&mut other)).await?;
+	assert_eq!(tomo.len(), 4);
+
+	let paths = tomo
+		.all_paths()
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()?;
+	assert_eq!(
+		paths,
+		vec![
+			Path::from_names(&["a"]),
+			Path::from_names(&["b"]),
+			Path::from_names(&["c"]),
+			Path::from_names(&["d"])
+		]
+	);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn indexed_paths() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	writer.add_dir(Path::from_names(&["dir"]), Some(Attributes::new(0o755)));
+	writer.add_file(
+		Path::from_names(&["dir", "a"]),
+		Some(Attributes::new(0o644)),
+		&b"hello"[..],
+	);
+	writer.add_file(Path::from_names(&["dir", "b"]), None, &b"world!"[..]);
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+
+	let mut data = output.into_inner();
+	data.extend(files(&[&["c"]]).await?);
+	let mut reader = Cursor::new(data);
+
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let items = tomo
+		.indexed_paths()
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let summary = items
+		.iter()
+		.map(|item| {
+			(
+				item.indic.kind,
+				item.path.clone(),
+				item.attrs.as_ref().map(|a| a.mode),
+			)
+		})
+		.collect::<Vec<_>>();
+	assert_eq!(
+		summary,
+		vec![
+			(IndicKind::Dir, Path::from_names(&["dir"]), Some(0o755)),
+			(
+				IndicKind::File,
+				Path::from_names(&["dir", "a"]),
+				Some(0o644)
+			),
+			(IndicKind::File, Path::from_names(&["dir", "b"]), None),
+			(IndicKind::File, Path::from_names(&["c"]), Some(0o644)),
+		]
+	);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_lookup_offset() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	writer.set_integrity(None);
+	writer.add_file(Path::from_names(&["a"]), None, &b"a"[..]);
+	let mut data = archive(writer).await?;
+	// the offset of the only lookup, past the count and its index
+	let at = entry_data(&data, IndicKind::Paths) + 4 + 4;
+	data[at..at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	let paths = tomo.all_paths().collect::<Vec<_>>().await;
+	assert!(matches!(paths[..], [Err(TomoError::Parse(_))]));
+
+	Ok(())
+}
@@ -0,0 +1,724 @@
+use common::{archive, container, entry_data};
+use eyre::Result;
+use futures::io::{AsyncReadExt, Cursor};
+use tomo::parsers::{Attributes, IndicKind, Mode, Path, Timestamp};
+use tomo::prelude::*;
+use tomo::Glob;
+
+mod common;
+
+/// A container without an Integrity entry, and where its ReversePaths data starts in it.
+async fn reverse_paths(paths: &[Path]) -> Result<(Vec<u8>, usize)> {
+	let mut writer = TomoWriter::default();
+	writer.set_integrity(None);
+	for path in paths {
+		writer.add_file(path.clone(), None, &b""[..]);
+	}
+	let data = archive(writer).await?;
+	let tree = entry_data(&data, IndicKind::ReversePaths);
+	Ok((data, tree))
+}
+
+#[async_std::test]
+async fn stacked() -> Result<()> {
+	let mut data = container(&[("a", "old a"), ("b", "old b")]).await?;
+	data.extend(container(&[("b", "new b"), ("c", "new c")]).await?);
+	let mut reader = Cursor::new(data);
+	let mut other = Cursor::new(container(&[("c", "other c")]).await?);
+
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	tomo.load(Seekable::new(&mut other)).await?;
+
+	let resolved = tomo.resolve().await?;
+	let summary = resolved
+		.iter()
+		.map(|item| (item.path.clone(), item.source, item.container))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		summary,
+		vec![
+			(Path::from_names(&["a"]), 0, 0),
+			(Path::from_names(&["b"]), 0, 1),
+			(Path::from_names(&["c"]), 1, 0),
+			(Path::from_names(&["dir"]), 1, 0),
+		]
+	);
+
+	let b = resolved.get(&Path::from_names(&["b"])).unwrap();
+	assert_eq!(b.indic.kind, IndicKind::File);
+	assert!(resolved.get(&Path::from_names(&["d"])).is_none());
+
+	let single = tomo.resolve_path(&Path::from_names(&["b"])).await?.unwrap();
+	assert_eq!((single.source, single.container), (0, 1));
+	assert_eq!(single.indic.offset, b.indic.offset);
+
+	let mut content = String::new();
+	tomo.open(&Path::from_names(&["b"]))
+		.await?
+		.unwrap()
+		.read_to_string(&mut content)
+		.await?;
+	assert_eq!(content, "new b");
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn empty() -> Result<()> {
+	let mut tomo = Tomo::default();
+	assert!(tomo.resolve().await?.is_empty());
+	assert!(tomo
+		.resolve_path(&Path::from_names(&["a"]))
+		.await?
+		.is_none());
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn overlay() -> Result<()> {
+	let mut base = TomoWriter::default();
+	base.add_dir(Path::from_names(&["dir"]), None);
+	base.add_file(Path::from_names(&["dir", "a"]), None, &b"base a"[..]);
+	base.add_dir(Path::from_names(&["gone"]), None);
+	base.add_file(Path::from_names(&["gone", "b"]), None, &b"base b"[..]);
+	base.add_file(Path::from_names(&["c"]), None, &b"base c"[..]);
+	base.add_file(Path::from_names(&["d"]), None, &b"base d"[..]);
+
+	let mut layer = TomoWriter::new(Mode::Overlay);
+	layer.add_opaque_dir(Path::from_names(&["dir"]), None);
+	layer.add_file(Path::from_names(&["dir", "new"]), None, &b"layer new"[..]);
+	layer.add_whiteout(Path::from_names(&["gone"]));
+	layer.add_whiteout(Path::from_names(&["c"]));
+
+	let mut output = Cursor::new(Vec::new());
+	base.finish(&mut output).await?;
+	layer.finish(&mut output).await?;
+	let mut reader = Cursor::new(output.into_inner());
+
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let resolved = tomo.resolve().await?;
+	let summary = resolved
+		.iter()
+		.map(|item| (item.path.clone(), item.indic.kind, item.container))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		summary,
+		vec![
+			(Path::from_names(&["d"]), IndicKind::File, 0),
+			(Path::from_names(&["dir"]), IndicKind::Dir, 1),
+			(Path::from_names(&["dir", "new"]), IndicKind::File, 1),
+		]
+	);
+
+	for gone in &[&["c"][..], &["gone"], &["gone", "b"], &["dir", "a"]] {
+		assert!(tomo.resolve_path(&Path::from_names(gone)).await?.is_none());
+		assert!(tomo.open(&Path::from_names(gone)).await?.is_none());
+	}
+
+	let dir = tomo
+		.resolve_path(&Path::from_names(&["dir"]))
+		.await?
+		.unwrap();
+	assert_eq!((dir.indic.kind, dir.container), (IndicKind::Dir, 1));
+
+	let mut content = String::new();
+	tomo.open(&Path::from_names(&["dir", "new"]))
+		.await?
+		.unwrap()
+		.read_to_string(&mut content)
+		.await?;
+	assert_eq!(content, "layer new");
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn markers_need_overlay() -> Result<()> {
+	let mut layer = TomoWriter::default();
+	layer.add_whiteout(Path::from_names(&["a"]));
+	layer.add_opaque_dir(Path::from_names(&["dir"]), None);
+
+	let mut data = container(&[("a", "kept")]).await?;
+	let mut output = Cursor::new(Vec::new());
+	layer.finish(&mut output).await?;
+	data.extend(output.into_inner());
+	let mut reader = Cursor::new(data);
+
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let resolved = tomo.resolve().await?;
+	assert_eq!(resolved.len(), 2);
+	assert!(tomo.open(&Path::from_names(&["a"])).await?.is_some());
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn primacy() -> Result<()> {
+	let mut output = Cursor::new(Vec::new());
+
+	let mut base = TomoWriter::new(Mode::Primacy);
+	base.add_file(Path::from_names(&["a"]), None, &b"base a"[..]);
+	base.add_file(Path::from_names(&["b"]), None, &b"base b"[..]);
+	base.finish(&mut output).await?;
+
+	let mut stacked = TomoWriter::default();
+	stacked.add_file(Path::from_names(&["a"]), None, &b"stacked a"[..]);
+	stacked.add_file(Path::from_names(&["c"]), None, &b"stacked c"[..]);
+	stacked.finish(&mut output).await?;
+
+	let mut overlay = TomoWriter::new(Mode::Overlay);
+	overlay.add_whiteout(Path::from_names(&["b"]));
+	overlay.finish(&mut output).await?;
+
+	let mut late = TomoWriter::new(Mode::Primacy);
+	late.add_file(Path::from_names(&["c"]), None, &b"late c"[..]);
+	late.add_file(Path::from_names(&["d"]), None, &b"late d"[..]);
+	late.finish(&mut output).await?;
+
+	let mut reader = Cursor::new(output.into_inner());
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let resolved = tomo.resolve().await?;
+	let summary = resolved
+		.iter()
+		.map(|item| (item.path.clone(), item.container))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		summary,
+		vec![
+			(Path::from_names(&["a"]), 0),
+			(Path::from_names(&["b"]), 0),
+			(Path::from_names(&["c"]), 1),
+			(Path::from_names(&["d"]), 3),
+		]
+	);
+
+	for (name, expected) in &[("a", "base a"), ("b", "base b"), ("c", "stacked c")] {
+		let mut content = String::new();
+		tomo.open(&Path::from_names(&[name]))
+			.await?
+			.unwrap()
+			.read_to_string(&mut content)
+			.await?;
+		assert_eq!(&content, expected);
+	}
+
+	Ok(())
+}
+
+fn modified(secs: i64) -> Option<Attributes> {
+	Some(Attributes {
+		mtime: Some(Timestamp { secs, nanos: 0 }),
+		..Attributes::new(0o644)
+	})
+}
+
+#[async_std::test]
+async fn newest() -> Result<()> {
+	let mut output = Cursor::new(Vec::new());
+
+	let mut first = TomoWriter::new(Mode::Newest);
+	first.add_file(Path::from_names(&["a"]), modified(100), &b"first a"[..]);
+	first.add_file(Path::from_names(&["b"]), modified(300), &b"first b"[..]);
+	first.finish(&mut output).await?;
+
+	let mut second = TomoWriter::new(Mode::Newest);
+	second.add_file(Path::from_names(&["a"]), modified(200), &b"second a"[..]);
+	second.add_file(Path::from_names(&["b"]), modified(200), &b"second b"[..]);
+	second.add_file(Path::from_names(&["c"]), None, &b"second c"[..]);
+	second.finish(&mut output).await?;
+
+	let mut third = TomoWriter::default();
+	third.add_file(Path::from_names(&["b"]), modified(300), &b"third b"[..]);
+	third.add_file(Path::from_names(&["c"]), modified(1), &b"third c"[..]);
+	third.finish(&mut output).await?;
+
+	let mut reader = Cursor::new(output.into_inner());
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let resolved = tomo.resolve().await?;
+	let summary = resolved
+		.iter()
+		.map(|item| (item.path.clone(), item.container))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		summary,
+		vec![
+			(Path::from_names(&["a"]), 1),
+			(Path::from_names(&["b"]), 2),
+			(Path::from_names(&["c"]), 2)
+		]
+	);
+
+	let a = tomo.resolve_path(&Path::from_names(&["a"])).await?.unwrap();
+	assert_eq!(a.container, 1);
+	assert_eq!(
+		a.attrs.and_then(|attrs| attrs.mtime),
+		Some(Timestamp {
+			secs: 200,
+			nanos: 0
+		})
+	);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn mixed() -> Result<()> {
+	let mut output = Cursor::new(Vec::new());
+
+	let mut newest = TomoWriter::new(Mode::Newest);
+	newest.add_file(Path::from_names(&["a"]), modified(500), &b"newest a"[..]);
+	newest.add_file(Path::from_names(&["b"]), modified(500), &b"newest b"[..]);
+	newest.add_file(Path::from_names(&["c"]), modified(500), &b"newest c"[..]);
+	newest.finish(&mut output).await?;
+
+	let mut stacked = TomoWriter::default();
+	stacked.add_file(Path::from_names(&["a"]), modified(100), &b"stacked a"[..]);
+	stacked.add_file(Path::from_names(&["b"]), modified(900), &b"stacked b"[..]);
+	stacked.finish(&mut output).await?;
+
+	let mut overlay = TomoWriter::new(Mode::Overlay);
+	overlay.add_whiteout(Path::from_names(&["c"]));
+	overlay.finish(&mut output).await?;
+
+	let mut reader = Cursor::new(output.into_inner());
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let resolved = tomo.resolve().await?;
+	let summary = resolved
+		.iter()
+		.map(|item| (item.path.clone(), item.container))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		summary,
+		vec![(Path::from_names(&["a"]), 0), (Path::from_names(&["b"]), 1)]
+	);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn mode_override() -> Result<()> {
+	let mut data = container(&[("a", "old a")]).await?;
+	data.extend(container(&[("a", "new a")]).await?);
+	let mut reader = Cursor::new(data);
+
+	let mut tomo = Tomo::default();
+	tomo.set_mode_override(Some(Mode::Primacy));
+	tomo.load(Seekable::new(&mut reader)).await?;
+	assert_eq!(tomo.mode_override(), Some(Mode::Primacy));
+
+	let resolved = tomo.resolve().await?;
+	assert_eq!(
+		resolved.get(&Path::from_names(&["a"])).unwrap().container,
+		0
+	);
+
+	let mut content = String::new();
+	tomo.open(&Path::from_names(&["a"]))
+		.await?
+		.unwrap()
+		.read_to_string(&mut content)
+		.await?;
+	assert_eq!(content, "old a");
+
+	tomo.set_mode_override(None);
+	let a = tomo.resolve_path(&Path::from_names(&["a"])).await?.unwrap();
+	assert_eq!(a.container, 1);
+
+	Ok(())
+}
+
+fn listing(items: &[tomo::IndexedPath]) -> Vec<(Path, IndicKind, usize)> {
+	items
+		.iter()
+		.map(|item| (item.path.clone(), item.indic.kind, item.container))
+		.collect()
+}
+
+#[async_std::test]
+async fn read_dir() -> Result<()> {
+	for reverse in &[true, false] {
+		let mut output = Cursor::new(Vec::new());
+		let mut first = TomoWriter::default();
+		first.set_reverse_paths(*reverse);
+		first.add_dir(Path::from_names(&["dir"]), None);
+		first.add_file(
+			Path::from_names(&["dir", "a"]),
+			Some(Attributes::new(0o600)),
+			&b"a"[..],
+		);
+		first.add_file(Path::from_names(&["dir", "b"]), None, &b"old b"[..]);
+		first.add_file(Path::from_names(&["dir", "deep", "c"]), None, &b"c"[..]);
+		first.add_file(Path::from_names(&["top"]), None, &b"top"[..]);
+		first.finish(&mut output).await?;
+
+		let mut second = TomoWriter::default();
+		second.set_reverse_paths(*reverse);
+		second.add_symlink(Path::from_names(&["dir", "b"]), None, b"a".to_vec());
+		second.add_dir(Path::from_names(&["dir", "deep"]), None);
+		second.finish(&mut output).await?;
+
+		let mut reader = Cursor::new(output.into_inner());
+		let mut tomo = Tomo::default();
+		tomo.load(Seekable::new(&mut reader)).await?;
+
+		let children = tomo.read_dir(&Path::from_names(&["dir"])).await?;
+		assert_eq!(
+			listing(&children),
+			vec![
+				(Path::from_names(&["dir", "a"]), IndicKind::File, 0),
+				(Path::from_names(&["dir", "b"]), IndicKind::Symlink, 1),
+				(Path::from_names(&["dir", "deep"]), IndicKind::Dir, 1),
+			]
+		);
+		assert_eq!(children[0].attrs.as_ref().unwrap().mode, 0o600);
+
+		assert_eq!(
+			listing(&tomo.read_dir(&Path::new(Vec::new())).await?),
+			vec![
+				(Path::from_names(&["dir"]), IndicKind::Dir, 0),
+				(Path::from_names(&["top"]), IndicKind::File, 0),
+			]
+		);
+		assert_eq!(
+			listing(&tomo.read_dir(&Path::from_names(&["dir", "deep"])).await?),
+			vec![(Path::from_names(&["dir", "deep", "c"]), IndicKind::File, 0)]
+		);
+		assert!(tomo.read_dir(&Path::from_names(&["top"])).await?.is_empty());
+		assert!(tomo
+			.read_dir(&Path::from_names(&["nowhere"]))
+			.await?
+			.is_empty());
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn read_dir_implicit() -> Result<()> {
+	for reverse in &[true, false] {
+		let mut writer = TomoWriter::default();
+		writer.set_reverse_paths(*reverse);
+		writer.add_file(Path::from_names(&["a", "b"]), None, &b"b"[..]);
+		writer.add_file(Path::from_names(&["a", "c", "d"]), None, &b"d"[..]);
+
+		let mut reader = Cursor::new(archive(writer).await?);
+		let mut tomo = Tomo::default();
+		tomo.load(Seekable::new(&mut reader)).await?;
+
+		let top = tomo.read_dir(&Path::new(Vec::new())).await?;
+		assert_eq!(
+			listing(&top),
+			vec![(Path::from_names(&["a"]), IndicKind::Dir, 0)]
+		);
+		assert!(top[0].attrs.is_none());
+		assert_eq!(
+			listing(&tomo.read_dir(&Path::from_names(&["a"])).await?),
+			vec![
+				(Path::from_names(&["a", "b"]), IndicKind::File, 0),
+				(Path::from_names(&["a", "c"]), IndicKind::Dir, 0),
+			]
+		);
+		assert_eq!(
+			listing(&tomo.read_dir(&Path::from_names(&["a", "c"])).await?),
+			vec![(Path::from_names(&["a", "c", "d"]), IndicKind::File, 0)]
+		);
+		assert!(tomo
+			.read_dir(&Path::from_names(&["a", "b"]))
+			.await?
+			.is_empty());
+	}
+
+	// nothing is left under it, so it's not there at all
+	let mut base = TomoWriter::default();
+	base.add_file(Path::from_names(&["a", "b"]), None, &b"b"[..]);
+	let mut layer = TomoWriter::new(Mode::Overlay);
+	layer.add_whiteout(Path::from_names(&["a", "b"]));
+
+	let mut output = Cursor::new(Vec::new());
+	base.finish(&mut output).await?;
+	layer.finish(&mut output).await?;
+	let mut reader = Cursor::new(output.into_inner());
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	assert!(tomo.read_dir(&Path::new(Vec::new())).await?.is_empty());
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn read_dir_overlay() -> Result<()> {
+	let mut base = TomoWriter::default();
+	base.add_dir(Path::from_names(&["dir"]), None);
+	base.add_file(Path::from_names(&["dir", "a"]), None, &b"base a"[..]);
+	base.add_dir(Path::from_names(&["dir", "sub"]), None);
+	base.add_file(Path::from_names(&["dir", "sub", "b"]), None, &b"base b"[..]);
+	base.add_dir(Path::from_names(&["kept"]), None);
+	base.add_file(Path::from_names(&["kept", "c"]), None, &b"base c"[..]);
+	base.add_file(Path::from_names(&["kept", "d"]), None, &b"base d"[..]);
+
+	let mut layer = TomoWriter::new(Mode::Overlay);
+	layer.add_opaque_dir(Path::from_names(&["dir"]), None);
+	layer.add_file(Path::from_names(&["dir", "new"]), None, &b"layer new"[..]);
+	layer.add_whiteout(Path::from_names(&["kept", "c"]));
+
+	let mut output = Cursor::new(Vec::new());
+	base.finish(&mut output).await?;
+	layer.finish(&mut output).await?;
+	let mut reader = Cursor::new(output.into_inner());
+
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	assert_eq!(
+		listing(&tomo.read_dir(&Path::from_names(&["dir"])).await?),
+		vec![(Path::from_names(&["dir", "new"]), IndicKind::File, 1)]
+	);
+	assert!(tomo
+		.read_dir(&Path::from_names(&["dir", "sub"]))
+		.await?
+		.is_empty());
+	assert_eq!(
+		listing(&tomo.read_dir(&Path::from_names(&["kept"])).await?),
+		vec![(Path::from_names(&["kept", "d"]), IndicKind::File, 0)]
+	);
+	assert_eq!(
+		listing(&tomo.read_dir(&Path::new(Vec::new())).await?),
+		vec![
+			(Path::from_names(&["dir"]), IndicKind::Dir, 1),
+			(Path::from_names(&["kept"]), IndicKind::Dir, 0),
+		]
+	);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn resolve_glob() -> Result<()> {
+	for reverse in &[true, false] {
+		let mut base = TomoWriter::default();
+		base.set_reverse_paths(*reverse);
+		base.add_dir(Path::from_names(&["src"]), None);
+		base.add_file(Path::from_names(&["src", "lib.rs"]), None, &b"lib"[..]);
+		base.add_file(
+			Path::from_names(&["src", "deep", "mod.rs"]),
+			None,
+			&b"mod"[..],
+		);
+		base.add_file(
+			Path::from_names(&["src", "deep", "notes.txt"]),
+			None,
+			&b"notes"[..],
+		);
+		base.add_file(
+			Path::from_names(&["src", "gone", "old.rs"]),
+			None,
+			&b"old"[..],
+		);
+		base.add_file(
+			Path::from_names(&["assets", "img", "a.png"]),
+			None,
+			&b"png"[..],
+		);
+		base.add_file(
+			Path::from_names(&["assets", "imgs.rs"]),
+			None,
+			&b"not under"[..],
+		);
+		base.add_file(Path::from_names(&["main.rs"]), None, &b"main"[..]);
+
+		let mut layer = TomoWriter::new(Mode::Overlay);
+		layer.set_reverse_paths(*reverse);
+		layer.add_whiteout(Path::from_names(&["src", "gone"]));
+		layer.add_file(
+			Path::from_names(&["src", "deep", "mod.rs"]),
+			None,
+			&b"new mod"[..],
+		);
+		layer.add_dir(Path::from_names(&["assets", "img"]), None);
+
+		let mut output = Cursor::new(Vec::new());
+		base.finish(&mut output).await?;
+		layer.finish(&mut output).await?;
+		let mut reader = Cursor::new(output.into_inner());
+
+		let mut tomo = Tomo::default();
+		tomo.load(Seekable::new(&mut reader)).await?;
+
+		let rust = Glob::new("src/**/*.rs")?;
+		let matched = tomo.resolve_glob(&rust).await?;
+		assert_eq!(
+			listing(&matched.iter().cloned().collect::<Vec<_>>()),
+			vec![
+				(Path::from_names(&["src", "lib.rs"]), IndicKind::File, 0),
+				(
+					Path::from_names(&["src", "deep", "mod.rs"]),
+					IndicKind::File,
+					1
+				),
+			]
+		);
+
+		let everything = tomo.resolve().await?;
+		let filtered = everything
+			.iter()
+			.filter(|item| rust.matches(&item.path))
+			.map(|item| (item.path.clone(), item.container, item.indic.offset))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			matched
+				.iter()
+				.map(|item| (item.path.clone(), item.container, item.indic.offset))
+				.collect::<Vec<_>>(),
+			filtered
+		);
+
+		let images = tomo
+			.resolve_glob(&Glob::under(&Path::from_names(&["assets", "img"])))
+			.await?;
+		assert_eq!(
+			listing(&images.iter().cloned().collect::<Vec<_>>()),
+			vec![
+				(Path::from_names(&["assets", "img"]), IndicKind::Dir, 1),
+				(
+					Path::from_names(&["assets", "img", "a.png"]),
+					IndicKind::File,
+					0
+				),
+			]
+		);
+
+		assert!(tomo
+			.resolve_glob(&Glob::new("nothing/**")?)
+			.await?
+			.is_empty());
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_reverse_counts() -> Result<()> {
+	let (mut data, tree) = reverse_paths(&[Path::from_names(&["a"])]).await?;
+	// the root's count of indics
+	data[tree..tree + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	assert!(tomo.resolve_path(&Path::new(Vec::new())).await.is_err());
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn read_dir_shadowed() -> Result<()> {
+	let mut output = Cursor::new(Vec::new());
+	let mut first = TomoWriter::default();
+	first.add_dir(Path::from_names(&["x"]), None);
+	first.add_file(Path::from_names(&["x", "a"]), None, &b"a"[..]);
+	first.finish(&mut output).await?;
+
+	let mut second = TomoWriter::default();
+	second.add_file(Path::from_names(&["x"]), None, &b"x"[..]);
+	second.finish(&mut output).await?;
+
+	let mut reader = Cursor::new(output.into_inner());
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	assert!(tomo.read_dir(&Path::from_names(&["x"])).await?.is_empty());
+	assert_eq!(
+		listing(&tomo.read_dir(&Path::new(Vec::new())).await?),
+		vec![(Path::from_names(&["x"]), IndicKind::File, 1)]
+	);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_reverse_children() -> Result<()> {
+	let (mut data, tree) = reverse_paths(&[Path::from_names(&["a"])]).await?;
+	// the root has no indics, so its count of children is next
+	data[tree + 4..tree + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	assert!(tomo.read_dir(&Path::new(Vec::new())).await.is_err());
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn reverse_paths_loop() -> Result<()> {
+	let (mut data, tree) =
+		reverse_paths(&[Path::from_names(&["a"]), Path::from_names(&["a", "b"])]).await?;
+	let u32_at = |data: &[u8], at: usize| {
+		u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]) as usize
+	};
+
+	// the root has no indics and one child, a
+	let a = u32_at(&data, tree + 8);
+	let a_node = a + 4 + u32_at(&data, tree + a);
+	// a has one indic and one child, b: point that back to a
+	let b_entry = tree + a_node + 4 + 8 + 4;
+	data[b_entry..b_entry + 8].copy_from_slice(&(a as u64).to_le_bytes());
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	assert!(tomo.resolve_glob(&Glob::new("**")?).await.is_err());
+	assert!(tomo.read_dir(&Path::from_names(&["a"])).await.is_err());
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn reverse_paths_without_paths() -> Result<()> {
+	let (mut data, _) = reverse_paths(&[Path::from_names(&["a"])]).await?;
+	// an unknown encoding, so the Paths entry can't be read at all
+	let paths = entry_data(&data, IndicKind::Paths);
+	data[paths - 1] = 0x02;
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	assert!(tomo.open(&Path::from_names(&["a"])).await?.is_some());
+	assert_eq!(
+		listing(&tomo.read_dir(&Path::new(Vec::new())).await?),
+		vec![(Path::from_names(&["a"]), IndicKind::File, 0)]
+	);
+	assert!(tomo.resolve().await.is_err());
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn reverse_paths_shared_children() -> Result<()> {
+	let (mut data, tree) =
+		reverse_paths(&[Path::from_names(&["a", "x"]), Path::from_names(&["b", "y"])]).await?;
+	// the root has no indics and two children: point b at a, so a's node has two parents
+	let (a, b) = (tree + 8, tree + 16);
+	let offset = data[a..a + 8].to_vec();
+	data[b..b + 8].copy_from_slice(&offset);
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	assert!(tomo.resolve_glob(&Glob::new("**")?).await.is_err());
+	assert!(tomo.read_dir(&Path::new(Vec::new())).await.is_err());
+
+	Ok(())
+}
@@ -0,0 +1,162 @@
+use common::{archive, entry_data};
+use deku::DekuContainerRead;
+use eyre::Result;
+use futures::io::{AsyncReadExt, Cursor};
+use tomo::parsers::{Device, IndicKind, Path};
+use tomo::prelude::*;
+
+mod common;
+
+async fn special() -> Result<Vec<u8>> {
+	let mut writer = TomoWriter::default();
+	writer.add_hardlink(
+		Path::from_names(&["link"]),
+		None,
+		Path::from_names(&["file"]),
+	);
+	writer.add_file(Path::from_names(&["file"]), None, &b"content"[..]);
+	writer.add_symlink(
+		Path::from_names(&["sym"]),
+		None,
+		b"../somewhere/else".to_vec(),
+	);
+	writer.add_char_device(
+		Path::from_names(&["null"]),
+		None,
+		Device { major: 1, minor: 3 },
+	);
+	writer.add_block_device(
+		Path::from_names(&["sda"]),
+		None,
+		Device { major: 8, minor: 0 },
+	);
+	writer.add_fifo(Path::from_names(&["pipe"]), None);
+	writer.add_socket(Path::from_names(&["sock"]), None);
+
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+	Ok(output.into_inner())
+}
+
+#[async_std::test]
+async fn kinds() -> Result<()> {
+	let mut reader = Cursor::new(special().await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let resolved = tomo.resolve().await?;
+	let kinds = resolved
+		.iter()
+		.map(|item| (item.path.clone(), item.indic.kind))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		kinds,
+		vec![
+			(Path::from_names(&["file"]), IndicKind::File),
+			(Path::from_names(&["link"]), IndicKind::Hardlink),
+			(Path::from_names(&["null"]), IndicKind::CharDevice),
+			(Path::from_names(&["pipe"]), IndicKind::Fifo),
+			(Path::from_names(&["sda"]), IndicKind::BlockDevice),
+			(Path::from_names(&["sock"]), IndicKind::Socket),
+			(Path::from_names(&["sym"]), IndicKind::Symlink),
+		]
+	);
+
+	let null = resolved.get(&Path::from_names(&["null"])).unwrap();
+	let mut data = Vec::new();
+	tomo.open_indexed(null)
+		.await?
+		.read_to_end(&mut data)
+		.await?;
+	let (_, device) = Device::from_bytes((&data, 0))?;
+	assert_eq!(device, Device { major: 1, minor: 3 });
+
+	let pipe = resolved.get(&Path::from_names(&["pipe"])).unwrap();
+	assert_eq!(pipe.indic.length, 0);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn links() -> Result<()> {
+	let mut reader = Cursor::new(special().await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	assert_eq!(
+		tomo.read_link(&Path::from_names(&["sym"])).await?,
+		Some(b"../somewhere/else".to_vec())
+	);
+	assert_eq!(tomo.read_link(&Path::from_names(&["nope"])).await?, None);
+	match tomo.read_link(&Path::from_names(&["file"])).await {
+		Err(TomoError::NotASymlink(IndicKind::File)) => {}
+		other => panic!("expected NotASymlink, got {:?}", other),
+	}
+
+	let link = tomo
+		.resolve_path(&Path::from_names(&["link"]))
+		.await?
+		.unwrap();
+	let target = tomo.hardlink_target(&link).await?;
+	assert_eq!(target.path, Path::from_names(&["file"]));
+	assert_eq!(target.indic.kind, IndicKind::File);
+
+	let mut content = String::new();
+	tomo.open(&Path::from_names(&["link"]))
+		.await?
+		.unwrap()
+		.read_to_string(&mut content)
+		.await?;
+	assert_eq!(content, "content");
+
+	match tomo.open(&Path::from_names(&["sym"])).await {
+		Err(TomoError::NotAFile(IndicKind::Symlink)) => {}
+		other => panic!("expected NotAFile, got {:?}", other),
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn missing_link_target() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	writer.add_hardlink(
+		Path::from_names(&["link"]),
+		None,
+		Path::from_names(&["nowhere"]),
+	);
+
+	let mut output = Cursor::new(Vec::new());
+	match writer.finish(&mut output).await {
+		Err(TomoError::MissingLinkTarget) => {}
+		other => panic!("expected MissingLinkTarget, got {:?}", other),
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn corrupt_link_target() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	writer.set_integrity(None);
+	writer.add_hardlink(
+		Path::from_names(&["link"]),
+		None,
+		Path::from_names(&["file"]),
+	);
+	writer.add_file(Path::from_names(&["file"]), None, &b"content"[..]);
+	let mut data = archive(writer).await?;
+	// far enough that its offset in the index overflows
+	let at = entry_data(&data, IndicKind::Hardlink);
+	data[at..at + 8].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	match tomo.open(&Path::from_names(&["link"])).await {
+		Err(TomoError::MissingLinkTarget) => {}
+		other => panic!("expected MissingLinkTarget, got {:?}", other.map(|_| ())),
+	}
+
+	Ok(())
+}
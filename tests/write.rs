@@ -0,0 +1,268 @@
+use deku::DekuContainerRead;
+use eyre::Result;
+use futures::io::Cursor;
+use tomo::parsers::{
+	self, Attributes, ContainerHeader, Indic, IndicKind, Path, PathSeg, Timestamp,
+	CONTAINER_HEADER_SIZE, INDIC_SIZE,
+};
+use tomo::prelude::*;
+
+#[async_std::test]
+async fn empty_writer() -> Result<()> {
+	let mut output = Cursor::new(Vec::new());
+	TomoWriter::default().finish(&mut output).await?;
+
+	let mut data = Vec::new();
+	data.extend(&parsers::MAGIC);
+	data.push(parsers::Mode::Stacked as u8);
+	data.extend(&0_u64.to_le_bytes());
+	data.extend(&0_u64.to_le_bytes());
+	assert_eq!(output.into_inner(), data);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn file_and_dir() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	writer.add_dir(Path::from_names(&["dir"]), Some(Attributes::new(0o755)));
+	writer.add_file(
+		Path::from_names(&["dir", "hello"]),
+		Some(Attributes::new(0o644)),
+		&b"Hello world!"[..],
+	);
+
+	let mut output = Cursor::new(Vec::new());
+	let header = writer.finish(&mut output).await?;
+	assert_eq!(header.index_bytes, 7 * INDIC_SIZE);
+
+	let data = output.into_inner();
+	assert_eq!(
+		data.len() as u64,
+		CONTAINER_HEADER_SIZE as u64 + header.index_bytes + header.entries_bytes
+	);
+
+	let ((rest, _), read) = ContainerHeader::from_bytes((&data, 0))?;
+	assert_eq!(read.index_bytes, header.index_bytes);
+	assert_eq!(read.entries_bytes, header.entries_bytes);
+
+	let mut rest = rest;
+	let mut index = Vec::new();
+	for _ in 0..7 {
+		let ((r, _), indic) = Indic::from_bytes((rest, 0))?;
+		index.push(indic);
+		rest = r;
+	}
+
+	let kinds = index.iter().map(|i| i.kind).collect::<Vec<_>>();
+	assert_eq!(
+		kinds,
+		vec![
+			IndicKind::Integrity,
+			IndicKind::Paths,
+			IndicKind::Attributes,
+			IndicKind::ReversePaths,
+			IndicKind::Checksums,
+			IndicKind::Dir,
+			IndicKind::File
+		]
+	);
+	assert_eq!((index[5].path, index[5].attrs), (1, 1));
+	assert_eq!((index[6].path, index[6].attrs), (2, 2));
+	assert_eq!(index[5].length, 0);
+
+	let file = index[6];
+	let entry = &rest[file.offset as usize..(file.offset + file.length) as usize];
+	assert_eq!(entry, b"\0\0Hello world!");
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	let ss = tomo.load(Seekable::new(&mut reader)).await?;
+	assert_eq!(ss.len(), 1);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn shared_attributes() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	for name in &["a", "b", "c"] {
+		writer.add_file(
+			Path::from_names(&[name]),
+			Some(Attributes::new(0o644)),
+			&b""[..],
+		);
+	}
+
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+	let data = output.into_inner();
+
+	let mut rest = &data[CONTAINER_HEADER_SIZE..];
+	let mut attrs = Vec::new();
+	for _ in 0..8 {
+		let ((r, _), indic) = Indic::from_bytes((rest, 0))?;
+		attrs.push(indic.attrs);
+		rest = r;
+	}
+	assert_eq!(attrs, vec![0, 0, 0, 0, 0, 1, 1, 1]);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn rich_attributes() -> Result<()> {
+	let rich = Attributes {
+		mtime: Some(Timestamp {
+			secs: 1_600_000_000,
+			nanos: 123_456_789,
+		}),
+		uid: Some(1000),
+		gid: Some(1000),
+		user: Some(b"user".to_vec()),
+		group: Some(b"group".to_vec()),
+		..Attributes::new(0o2755)
+	};
+
+	let mut writer = TomoWriter::default();
+	writer.add_dir(Path::from_names(&["plain"]), Some(Attributes::new(0o755)));
+	writer.add_dir(Path::from_names(&["rich"]), Some(rich.clone()));
+
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+	let data = output.into_inner();
+
+	let (_, indic) =
+		Indic::from_bytes((&data[CONTAINER_HEADER_SIZE + 2 * INDIC_SIZE as usize..], 0))?;
+	assert_eq!(indic.kind, IndicKind::AttributesV2);
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let attrs = tomo
+		.indexed_paths()
+		.map(|item| item.map(|item| item.attrs))
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()?;
+	assert_eq!(attrs, vec![Some(Attributes::new(0o755)), Some(rich)]);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn reverse_paths() -> Result<()> {
+	let paths = [
+		Path::from_names(&["a"]),
+		Path::from_names(&["a", "b"]),
+		Path::from_names(&["a", "b", "c"]),
+		Path::from_names(&["a", "ba"]),
+		Path::new(vec![PathSeg::Root, PathSeg::from_name("abs")]),
+		Path::from_names(&["z"]),
+	];
+
+	let mut containers = Vec::new();
+	for reverse in &[true, false] {
+		let mut writer = TomoWriter::default();
+		writer.set_reverse_paths(*reverse);
+		for (n, path) in paths.iter().enumerate() {
+			writer.add_file(path.clone(), Some(Attributes::new(n as u16)), &b""[..]);
+		}
+		writer.add_opaque_dir(Path::from_names(&["z"]), None);
+
+		let mut output = Cursor::new(Vec::new());
+		writer.finish(&mut output).await?;
+		containers.push(output.into_inner());
+	}
+
+	let kinds = |data: &[u8]| {
+		let index = &data[CONTAINER_HEADER_SIZE..];
+		(0..4)
+			.map(|n| {
+				let (_, indic) =
+					Indic::from_bytes((&index[(n * INDIC_SIZE) as usize..], 0)).unwrap();
+				indic.kind
+			})
+			.collect::<Vec<_>>()
+	};
+	assert_eq!(kinds(&containers[0])[3], IndicKind::ReversePaths);
+	assert_ne!(kinds(&containers[1])[3], IndicKind::ReversePaths);
+
+	let mut found = Vec::new();
+	for data in containers {
+		let mut reader = Cursor::new(data);
+		let mut tomo = Tomo::default();
+		tomo.load(Seekable::new(&mut reader)).await?;
+
+		let mut lookups = Vec::new();
+		for path in paths.iter().chain(&[
+			Path::new(vec![]),
+			Path::from_names(&["b"]),
+			Path::from_names(&["a", "c"]),
+			Path::from_names(&["abs"]),
+		]) {
+			let resolved = tomo.resolve_path(path).await?;
+			lookups.push(resolved.map(|r| (r.indic.kind, r.attrs.map(|a| a.mode))));
+		}
+		found.push(lookups);
+	}
+
+	assert_eq!(
+		found[0],
+		vec![
+			Some((IndicKind::File, Some(0))),
+			Some((IndicKind::File, Some(1))),
+			Some((IndicKind::File, Some(2))),
+			Some((IndicKind::File, Some(3))),
+			Some((IndicKind::File, Some(4))),
+			Some((IndicKind::Dir, None)),
+			None,
+			None,
+			None,
+			None,
+		]
+	);
+	assert_eq!(found[0], found[1]);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn names_round_trip() -> Result<()> {
+	let names: &[&[u8]] = &[b"plain", b"with space", b"\xFF\xFEbytes", b"\x01\x7F"];
+	let mut writer = TomoWriter::default();
+	for name in names {
+		writer.add_file(Path::from_names(&[name]), None, *name);
+	}
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+
+	let mut reader = Cursor::new(output.into_inner());
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	for name in names {
+		let found = tomo.resolve_path(&Path::from_names(&[name])).await?;
+		assert_eq!(found.unwrap().path.segments()[0].name(), Some(*name));
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn nul_in_name() -> Result<()> {
+	let bad = Path::from_names(&[&b"dir"[..], b"a\0b"]);
+	let mut writer = TomoWriter::default();
+	writer.add_file(Path::from_names(&["fine"]), None, &b""[..]);
+	writer.add_file(bad.clone(), None, &b""[..]);
+
+	let mut output = Cursor::new(Vec::new());
+	match writer.finish(&mut output).await {
+		Err(TomoError::InvalidPath(path)) => assert_eq!(path, bad),
+		other => panic!("expected InvalidPath, got {:?}", other),
+	}
+	assert!(output.into_inner().is_empty());
+
+	Ok(())
+}
@@ -0,0 +1,56 @@
+#![cfg(target_os = "linux")]
+
+use eyre::Result;
+use futures::io::Cursor;
+use std::{env, fs, process};
+use tomo::parsers::{Attributes, Path, PathSeg};
+use tomo::prelude::*;
+
+#[async_std::test]
+async fn roundtrip_through_container() -> Result<()> {
+	let dir = env::temp_dir().join(format!("tomo-xattrs-{}", process::id()));
+	fs::create_dir_all(&dir)?;
+	let original = dir.join("original");
+	let restored = dir.join("restored");
+	fs::write(&original, b"data")?;
+	fs::write(&restored, b"data")?;
+
+	let mut attrs = Attributes::new(0o644);
+	attrs
+		.xattrs
+		.insert(b"user.tomo.colour".to_vec(), b"blue".to_vec());
+	attrs.restore_xattrs(&original)?;
+
+	let mut read = Attributes::new(0o644);
+	read.read_xattrs(&original)?;
+	if read.xattrs.is_empty() {
+		// filesystem doesn't support user xattrs
+		fs::remove_dir_all(&dir)?;
+		return Ok(());
+	}
+	assert_eq!(
+		read.xattrs.get(&b"user.tomo.colour"[..]),
+		Some(&b"blue".to_vec())
+	);
+
+	let mut writer = TomoWriter::default();
+	let path = Path::new(vec![PathSeg::Segment(b"file\0".to_vec())]);
+	writer.add_file(path, Some(read.clone()), &b"data"[..]);
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+
+	let mut reader = Cursor::new(output.into_inner());
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+	let item = tomo.indexed_paths().next().await.unwrap()?;
+	let unpacked = item.attrs.unwrap();
+	assert_eq!(unpacked, read);
+
+	unpacked.restore_xattrs(&restored)?;
+	let mut check = Attributes::new(0o644);
+	check.read_xattrs(&restored)?;
+	assert_eq!(check.xattrs, read.xattrs);
+
+	fs::remove_dir_all(&dir)?;
+	Ok(())
+}
@@ -0,0 +1,259 @@
+use eyre::Result;
+use futures::io::{AsyncReadExt, Cursor};
+use tomo::parsers::{Attributes, Encoding, IndicKind, Path};
+use tomo::prelude::*;
+use tomo::Compression;
+
+fn big() -> Vec<u8> {
+	(0..1_000_000_u32)
+		.map(|n| (n % 251) as u8)
+		.collect::<Vec<_>>()
+}
+
+async fn archive(data: Compression, metadata: Compression) -> Result<Vec<u8>> {
+	let big = big();
+	let mut writer = TomoWriter::default();
+	writer.set_compression(data);
+	writer.set_metadata_compression(metadata);
+	writer.add_file(
+		Path::from_names(&["small"]),
+		Some(Attributes::new(0o644)),
+		&b"tiny file"[..],
+	);
+	writer.add_file(
+		Path::from_names(&["big"]),
+		Some(Attributes::new(0o600)),
+		&big[..],
+	);
+
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+	Ok(output.into_inner())
+}
+
+#[async_std::test]
+async fn compressed_data() -> Result<()> {
+	let raw = archive(Compression::None, Compression::None).await?;
+	let data = archive(Compression::Zstd { level: 3 }, Compression::None).await?;
+	assert!(data.len() < raw.len() / 10);
+
+	let mut reader = Cursor::new(data);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	{
+		let mut content = Vec::new();
+		let mut file = tomo.open(&Path::from_names(&["big"])).await?.unwrap();
+		assert_eq!(file.header().encoding(), Encoding::Zstd);
+		file.read_to_end(&mut content).await?;
+		assert_eq!(content, big());
+	}
+
+	{
+		let mut content = Vec::new();
+		let mut file = tomo.open(&Path::from_names(&["small"])).await?.unwrap();
+		file.read_to_end(&mut content).await?;
+		assert_eq!(content, b"tiny file");
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn compressed_metadata() -> Result<()> {
+	let level = Compression::Zstd { level: 19 };
+	let mut reader = Cursor::new(archive(level, level).await?);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let items = tomo
+		.indexed_paths()
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()?;
+	let summary = items
+		.iter()
+		.map(|item| {
+			(
+				item.indic.kind,
+				item.path.clone(),
+				item.attrs.as_ref().map(|a| a.mode),
+			)
+		})
+		.collect::<Vec<_>>();
+	assert_eq!(
+		summary,
+		vec![
+			(IndicKind::File, Path::from_names(&["small"]), Some(0o644)),
+			(IndicKind::File, Path::from_names(&["big"]), Some(0o600)),
+		]
+	);
+
+	let ss = tomo.source_mut(0).unwrap();
+	let mut entries = ss.entries(0).unwrap();
+	while let Some(entry) = entries.next().await {
+		let mut entry = entry?;
+		if matches!(
+			entry.indic.kind,
+			IndicKind::Checksums | IndicKind::Integrity
+		) {
+			assert_eq!(entry.reader.header().encoding(), Encoding::Raw);
+			continue;
+		}
+		assert_eq!(entry.reader.header().encoding(), Encoding::Zstd);
+		let mut data = Vec::new();
+		entry.reader.read_to_end(&mut data).await?;
+		if entry.indic.kind == IndicKind::File && entry.indic.path == 1 {
+			assert_eq!(data, b"tiny file");
+		}
+	}
+
+	Ok(())
+}
+
+fn json(n: usize) -> String {
+	format!(
+		r#"{{"id":{},"name":"item number {}","tags":["alpha","beta","gamma"],"enabled":true}}"#,
+		n, n
+	)
+}
+
+async fn small_files(dictionary: Option<Vec<u8>>) -> Result<Vec<u8>> {
+	let files = (0..100).map(json).collect::<Vec<_>>();
+
+	let mut writer = TomoWriter::default();
+	writer.set_compression(Compression::Zstd { level: 3 });
+	if let Some(dict) = dictionary {
+		writer.set_dictionary(dict);
+	}
+	for (n, file) in files.iter().enumerate() {
+		writer.add_file(
+			Path::from_names(&[&format!("{}.json", n)]),
+			None,
+			file.as_bytes(),
+		);
+	}
+
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+	Ok(output.into_inner())
+}
+
+#[async_std::test]
+async fn dictionary() -> Result<()> {
+	let dict = (1000..1010).map(json).collect::<String>().into_bytes();
+	let without = small_files(None).await?;
+	let with = small_files(Some(dict.clone())).await?;
+	assert!(with.len() + dict.len() < without.len());
+
+	let mut reader = Cursor::new(with);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	for n in &[0, 42, 99] {
+		let mut content = String::new();
+		let mut file = tomo
+			.open(&Path::from_names(&[&format!("{}.json", n)]))
+			.await?
+			.unwrap();
+		assert_eq!(file.header().encoding(), Encoding::Zstd);
+		assert!(!file.header().params().is_empty());
+		file.read_to_string(&mut content).await?;
+		assert_eq!(content, json(*n));
+	}
+
+	let ss = tomo.source_mut(0).unwrap();
+	let mut entries = ss.entries(0).unwrap();
+	let mut files = 0;
+	while let Some(entry) = entries.next().await {
+		let mut entry = entry?;
+		let mut data = Vec::new();
+		entry.reader.read_to_end(&mut data).await?;
+		match entry.indic.kind {
+			IndicKind::Dictionary => assert_eq!(data, dict),
+			IndicKind::File => files += 1,
+			_ => {}
+		}
+	}
+	assert_eq!(files, 100);
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn trained_dictionary() -> Result<()> {
+	let files = (0..1000).map(json).collect::<Vec<_>>();
+	let large = big();
+
+	let pack = |train: bool| {
+		let mut writer = TomoWriter::default();
+		writer.set_compression(Compression::Zstd { level: 3 });
+		if train {
+			writer.set_dictionary_training(4096);
+		}
+		for (n, file) in files.iter().enumerate() {
+			writer.add_file(
+				Path::from_names(&[&format!("{}.json", n)]),
+				None,
+				file.as_bytes(),
+			);
+		}
+		writer.add_file(Path::from_names(&["big"]), None, &large[..]);
+		async move {
+			let mut output = Cursor::new(Vec::new());
+			writer.finish(&mut output).await?;
+			Result::<_>::Ok(output.into_inner())
+		}
+	};
+
+	let without = pack(false).await?;
+	let with = pack(true).await?;
+	assert!(with.len() < without.len());
+
+	let mut reader = Cursor::new(with);
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	{
+		let mut content = String::new();
+		let mut file = tomo.open(&Path::from_names(&["123.json"])).await?.unwrap();
+		assert!(!file.header().params().is_empty());
+		file.read_to_string(&mut content).await?;
+		assert_eq!(content, json(123));
+	}
+
+	{
+		let mut content = Vec::new();
+		let mut file = tomo.open(&Path::from_names(&["big"])).await?.unwrap();
+		assert_eq!(file.header().encoding(), Encoding::Zstd);
+		assert!(file.header().params().is_empty());
+		file.read_to_end(&mut content).await?;
+		assert_eq!(content, large);
+	}
+
+	Ok(())
+}
+
+#[async_std::test]
+async fn too_little_to_train() -> Result<()> {
+	let mut writer = TomoWriter::default();
+	writer.set_compression(Compression::Zstd { level: 3 });
+	writer.set_dictionary_training(4096);
+	writer.add_file(Path::from_names(&["one"]), None, &b"just the one"[..]);
+
+	let mut output = Cursor::new(Vec::new());
+	writer.finish(&mut output).await?;
+
+	let mut reader = Cursor::new(output.into_inner());
+	let mut tomo = Tomo::default();
+	tomo.load(Seekable::new(&mut reader)).await?;
+
+	let mut content = String::new();
+	let mut file = tomo.open(&Path::from_names(&["one"])).await?.unwrap();
+	assert!(file.header().params().is_empty());
+	file.read_to_string(&mut content).await?;
+	assert_eq!(content, "just the one");
+
+	Ok(())
+}